@@ -0,0 +1,94 @@
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::collections::HashMap;
+use wampproto::messages::event::Event;
+use wampproto::messages::types::Value;
+use wampproto::serializers::cbor::CBORSerializer;
+use wampproto::serializers::helpers::to_message;
+use wampproto::serializers::json::JSONSerializer;
+use wampproto::serializers::msgpack::MsgPackSerializer;
+use wampproto::serializers::serializer::Serializer;
+
+fn event(size: usize) -> Event {
+    let args = (0..size)
+        .map(|i| {
+            Value::Dict(HashMap::from([
+                ("index".to_string(), Value::Int(i as i64)),
+                ("name".to_string(), Value::str(format!("item-{i}"))),
+            ]))
+        })
+        .collect();
+
+    let kwargs = HashMap::from([("tags".to_string(), Value::list(vec![Value::str("a"), Value::str("b")]))]);
+
+    Event {
+        subscription_id: 1,
+        publication_id: 2,
+        details: HashMap::new(),
+        args: Some(args),
+        kwargs: Some(kwargs),
+    }
+}
+
+fn serializers() -> Vec<(&'static str, Box<dyn Serializer>)> {
+    vec![
+        ("json", Box::new(JSONSerializer {})),
+        ("msgpack", Box::new(MsgPackSerializer {})),
+        ("cbor", Box::new(CBORSerializer {})),
+    ]
+}
+
+// Event::parse as it was before values were moved out of the decoded array: every field is cloned from a borrow
+fn parse_event_by_clone(data: &[Value]) -> Event {
+    match data {
+        [
+            _,
+            Value::Int(subscription_id),
+            Value::Int(publication_id),
+            Value::Dict(details),
+            Value::List(args),
+            Value::Dict(kwargs),
+        ] => Event {
+            subscription_id: *subscription_id,
+            publication_id: *publication_id,
+            details: details.clone(),
+            args: Some(args.clone()),
+            kwargs: Some(kwargs.clone()),
+        },
+        _ => panic!("unexpected event layout"),
+    }
+}
+
+// decoding a serialized EVENT into a message: typed straight off the wire (what the serializers do), through
+// an intermediate value array moved into the message, and the previous clone based parse of that array
+fn deserialize(c: &mut Criterion) {
+    for size in [1, 100] {
+        let mut group = c.benchmark_group(format!("deserialize/event-{size}"));
+        for (name, serializer) in serializers() {
+            let payload = serializer.serialize(&event(size)).unwrap();
+            group.throughput(Throughput::Bytes(payload.len() as u64));
+
+            group.bench_with_input(BenchmarkId::new("typed", name), &payload, |b, payload| {
+                b.iter(|| serializer.deserialize_slice(black_box(payload)).unwrap())
+            });
+
+            group.bench_with_input(BenchmarkId::new("move", name), &payload, |b, payload| {
+                b.iter(|| match serializer.deserialize_value(black_box(payload)).unwrap() {
+                    Value::List(values) => to_message(values).unwrap(),
+                    _ => unreachable!(),
+                })
+            });
+
+            group.bench_with_input(BenchmarkId::new("clone", name), &payload, |b, payload| {
+                b.iter(|| match serializer.deserialize_value(black_box(payload)).unwrap() {
+                    Value::List(values) => parse_event_by_clone(&values),
+                    _ => unreachable!(),
+                })
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, deserialize);
+criterion_main!(benches);
@@ -0,0 +1,463 @@
+use crate::authenticators::authenticator::{AuthOutcome, AuthRequest, AuthResponse, ServerAuthenticator};
+use crate::messages::authenticate::Authenticate;
+use crate::messages::challenge::Challenge;
+use crate::messages::hello::Hello;
+use crate::messages::message::Message;
+use crate::messages::types::{ERROR_NO_AUTH_METHOD, ERROR_NO_SUCH_REALM, Error, Value};
+use crate::messages::wamp_message::WampMessage;
+use crate::messages::welcome::Welcome;
+use crate::roles::Roles;
+use crate::serializers::serializer::Serializer;
+use crate::types::SessionDetails;
+use std::collections::HashMap;
+
+type AcceptorState = u8;
+
+const ACCEPTOR_STATE_NONE: AcceptorState = 0;
+const ACCEPTOR_STATE_CHALLENGE_SENT: AcceptorState = 1;
+const ACCEPTOR_STATE_WELCOME_SENT: AcceptorState = 2;
+const ACCEPTOR_STATE_ABORTED: AcceptorState = 3;
+
+const ANONYMOUS: &str = "anonymous";
+
+pub fn get_router_roles() -> HashMap<String, Value> {
+    Roles::router().marshal()
+}
+
+pub struct Acceptor {
+    state: AcceptorState,
+    session_id: i64,
+    session_details: Option<SessionDetails>,
+
+    serializer: Box<dyn Serializer>,
+    authenticators: Vec<Box<dyn ServerAuthenticator>>,
+    realms: Option<Vec<String>>,
+    roles: Roles,
+
+    authenticator: Option<Box<dyn ServerAuthenticator>>,
+    request: Option<AuthRequest>,
+    peer_roles: Roles,
+    challenge: Option<Challenge>,
+}
+
+impl Acceptor {
+    pub fn new(
+        session_id: i64,
+        serializer: Box<dyn Serializer>,
+        authenticators: Vec<Box<dyn ServerAuthenticator>>,
+    ) -> Self {
+        Acceptor {
+            state: ACCEPTOR_STATE_NONE,
+            session_id,
+            session_details: None,
+            serializer,
+            authenticators,
+            realms: None,
+            roles: Roles::router(),
+            authenticator: None,
+            request: None,
+            peer_roles: Roles::new(),
+            challenge: None,
+        }
+    }
+
+    pub fn with_realms(mut self, realms: Vec<String>) -> Self {
+        self.realms = Some(realms);
+        self
+    }
+
+    pub fn with_roles(mut self, roles: Roles) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    pub fn receive(&mut self, data: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+        match self.serializer.deserialize(data) {
+            Ok(msg_in) => match self.receive_message(msg_in) {
+                Ok(Some(msg_out)) => match self.serializer.serialize_message(&msg_out) {
+                    Ok(msg_out) => Ok(Some(msg_out)),
+                    Err(e) => Err(e),
+                },
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            },
+
+            Err(e) => Err(Error::serialization(format!(
+                "failed to deserialize message: {}",
+                e.message()
+            ))),
+        }
+    }
+
+    pub fn receive_message(&mut self, msg: WampMessage) -> Result<Option<WampMessage>, Error> {
+        match msg {
+            WampMessage::Hello(hello) => {
+                if self.state != ACCEPTOR_STATE_NONE {
+                    Err(Error::protocol_violation("received HELLO when it was not expected"))
+                } else {
+                    Ok(Some(self.receive_hello(&hello)))
+                }
+            }
+
+            WampMessage::Authenticate(authenticate) => {
+                if self.state != ACCEPTOR_STATE_CHALLENGE_SENT {
+                    Err(Error::protocol_violation(
+                        "received AUTHENTICATE when it was not expected",
+                    ))
+                } else {
+                    Ok(Some(self.receive_authenticate(&authenticate)))
+                }
+            }
+
+            WampMessage::Abort(mut abort) => {
+                self.state = ACCEPTOR_STATE_ABORTED;
+                let message = match abort.details.remove("message") {
+                    Some(Value::Str(message)) => message,
+                    _ => "session aborted by peer".to_string(),
+                };
+
+                Err(Error::application(abort.reason, message))
+            }
+
+            _ => Err(Error::protocol_violation(format!(
+                "received unknown message type {:?}",
+                msg.message_type()
+            ))),
+        }
+    }
+
+    fn receive_hello(&mut self, hello: &Hello) -> WampMessage {
+        if let Some(realms) = &self.realms
+            && !realms.contains(&hello.realm)
+        {
+            return self.abort(Error::application(
+                ERROR_NO_SUCH_REALM,
+                format!("realm '{}' does not exist", hello.realm),
+            ));
+        }
+
+        self.peer_roles = match Roles::parse(&hello.roles) {
+            Ok(roles) => roles,
+            Err(e) => return self.abort(e),
+        };
+
+        // the spec mandates anonymous authentication when the client does not announce any method
+        let methods = if hello.auth_methods.is_empty() {
+            vec![ANONYMOUS.to_string()]
+        } else {
+            hello.auth_methods.clone()
+        };
+
+        let authenticator = methods.iter().find_map(|method| {
+            self.authenticators
+                .iter()
+                .find(|authenticator| authenticator.auth_method() == *method)
+        });
+
+        let authenticator = match authenticator {
+            Some(authenticator) => authenticator.clone(),
+            None => {
+                return self.abort(Error::application(
+                    ERROR_NO_AUTH_METHOD,
+                    format!("none of the authmethods {methods:?} are supported"),
+                ));
+            }
+        };
+
+        let request = AuthRequest {
+            session_id: self.session_id,
+            realm: hello.realm.clone(),
+            authid: hello.authid.clone(),
+            auth_method: authenticator.auth_method(),
+            auth_extra: hello.auth_extra.clone(),
+        };
+
+        match authenticator.hello(&request) {
+            Ok(AuthOutcome::Welcome(response)) => self.welcome(&request, response),
+            Ok(AuthOutcome::Challenge(challenge)) => {
+                self.state = ACCEPTOR_STATE_CHALLENGE_SENT;
+                self.authenticator = Some(authenticator);
+                self.request = Some(request);
+                self.challenge = Some(Challenge {
+                    auth_method: challenge.auth_method.clone(),
+                    extra: challenge.extra.clone(),
+                });
+
+                WampMessage::Challenge(challenge)
+            }
+            Err(e) => self.abort(e),
+        }
+    }
+
+    fn receive_authenticate(&mut self, authenticate: &Authenticate) -> WampMessage {
+        let (authenticator, request, challenge) =
+            match (self.authenticator.take(), self.request.take(), self.challenge.take()) {
+                (Some(authenticator), Some(request), Some(challenge)) => (authenticator, request, challenge),
+                _ => {
+                    return self.abort(Error::authentication("authentication state is missing"));
+                }
+            };
+
+        match authenticator.authenticate(&request, &challenge, authenticate) {
+            Ok(response) => self.welcome(&request, response),
+            Err(e) => self.abort(e),
+        }
+    }
+
+    fn welcome(&mut self, request: &AuthRequest, response: AuthResponse) -> WampMessage {
+        let mut details = HashMap::new();
+        details.insert("authmethod".to_string(), Value::str(request.auth_method.clone()));
+        details.insert("roles".to_string(), Value::dict(self.roles.marshal()));
+
+        let welcome = Welcome::new(
+            self.session_id,
+            &request.realm,
+            &response.authid,
+            &response.auth_role,
+            details,
+        );
+
+        self.session_details = Some(
+            SessionDetails::new(
+                self.session_id,
+                request.realm.clone(),
+                response.authid,
+                response.auth_role,
+                self.serializer.is_static(),
+            )
+            .with_roles(self.peer_roles.clone()),
+        );
+        self.state = ACCEPTOR_STATE_WELCOME_SENT;
+
+        WampMessage::Welcome(welcome)
+    }
+
+    fn abort(&mut self, error: Error) -> WampMessage {
+        self.state = ACCEPTOR_STATE_ABORTED;
+        WampMessage::Abort(error.to_abort())
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.state == ACCEPTOR_STATE_ABORTED
+    }
+
+    pub fn session_details(&self) -> Result<Option<&SessionDetails>, Error> {
+        if self.session_details.is_none() {
+            Err(Error::invalid_state("session is not setup yet"))
+        } else {
+            Ok(self.session_details.as_ref())
+        }
+    }
+
+    // hands the established session over, e.g. to Router::attach_session
+    pub fn into_session_details(self) -> Result<SessionDetails, Error> {
+        self.session_details
+            .ok_or_else(|| Error::invalid_state("session is not setup yet"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authenticators::anonymous::AnonymousServerAuthenticator;
+    use crate::authenticators::authenticator::{ClientAuthenticator, Credential};
+    use crate::authenticators::cryptosign::{CryptoSignAuthenticator, CryptoSignServerAuthenticator};
+    use crate::authenticators::ticket::{TicketAuthenticator, TicketServerAuthenticator};
+    use crate::authenticators::wampcra::{WAMPCRAAuthenticator, WAMPCRAServerAuthenticator};
+    use crate::messages::abort::MESSAGE_TYPE_ABORT;
+    use crate::messages::types::{ERROR_AUTHENTICATION_FAILED, ERROR_PROTOCOL_VIOLATION};
+    use crate::messages::welcome::MESSAGE_TYPE_WELCOME;
+    use crate::serializers::json::JSONSerializer;
+
+    fn acceptor() -> Acceptor {
+        Acceptor::new(
+            1,
+            Box::new(JSONSerializer {}),
+            vec![Box::new(AnonymousServerAuthenticator::default())],
+        )
+        .with_realms(vec!["realm1".to_string()])
+    }
+
+    fn hello(realm: &str, methods: Vec<&str>) -> WampMessage {
+        let methods = methods.into_iter().map(String::from).collect();
+        WampMessage::Hello(Hello::new(
+            realm,
+            "",
+            HashMap::new(),
+            Roles::client().marshal(),
+            methods,
+        ))
+    }
+
+    fn abort_reason(message: Option<WampMessage>) -> String {
+        match message {
+            Some(WampMessage::Abort(abort)) => abort.reason,
+            other => panic!("expected ABORT, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn anonymous_welcome() {
+        let mut acceptor = acceptor();
+        let welcome = acceptor.receive_message(hello("realm1", vec![])).unwrap().unwrap();
+        assert_eq!(welcome.message_type(), MESSAGE_TYPE_WELCOME);
+
+        let welcome = match welcome {
+            WampMessage::Welcome(welcome) => welcome,
+            _ => unreachable!(),
+        };
+        assert_eq!(welcome.session_id, 1);
+        assert_eq!(welcome.realm, "realm1");
+        assert_eq!(welcome.authid, "1");
+        assert_eq!(welcome.auth_role, "anonymous");
+
+        let details = acceptor.into_session_details().unwrap();
+        assert_eq!((details.id, details.realm.as_str()), (1, "realm1"));
+        assert!(details.roles.has_role("caller"));
+    }
+
+    #[test]
+    fn unknown_realm_aborts() {
+        let mut acceptor = acceptor();
+        let reply = acceptor.receive_message(hello("realm2", vec![])).unwrap();
+        assert_eq!(abort_reason(reply), ERROR_NO_SUCH_REALM);
+        assert!(acceptor.is_aborted());
+    }
+
+    #[test]
+    fn unsupported_auth_method_aborts() {
+        let mut acceptor = acceptor();
+        let reply = acceptor.receive_message(hello("realm1", vec!["ticket"])).unwrap();
+        assert_eq!(abort_reason(reply), ERROR_NO_AUTH_METHOD);
+    }
+
+    #[test]
+    fn malformed_roles_abort_with_protocol_violation() {
+        let mut acceptor = acceptor();
+        let roles = HashMap::from([("caller".to_string(), Value::Int(1))]);
+        let hello = Hello::new("realm1", "", HashMap::new(), roles, vec![]);
+
+        let reply = acceptor.receive_message(WampMessage::Hello(hello)).unwrap();
+        assert_eq!(
+            reply.as_ref().map(|abort| abort.message_type()),
+            Some(MESSAGE_TYPE_ABORT)
+        );
+        assert_eq!(abort_reason(reply), ERROR_PROTOCOL_VIOLATION);
+    }
+
+    #[test]
+    fn unexpected_messages_are_rejected() {
+        let mut acceptor = acceptor();
+        let authenticate = Authenticate {
+            signature: String::new(),
+            extra: HashMap::new(),
+        };
+        assert!(
+            acceptor
+                .receive_message(WampMessage::Authenticate(authenticate))
+                .is_err()
+        );
+
+        acceptor.receive_message(hello("realm1", vec![])).unwrap();
+        assert!(acceptor.receive_message(hello("realm1", vec![])).is_err());
+    }
+
+    fn store(secret: &str) -> impl Fn(&AuthRequest) -> Result<Credential, Error> + Send + Sync + 'static {
+        let secret = secret.to_string();
+        move |request: &AuthRequest| match request.authid.as_str() {
+            "alice" => Ok(Credential::new("alice", "user", &secret)),
+            authid => Err(Error::authentication(format!("unknown authid {authid}"))),
+        }
+    }
+
+    // runs HELLO, CHALLENGE and AUTHENTICATE between the client authenticator and an acceptor, returning the final reply
+    fn authenticate(server: Box<dyn ServerAuthenticator>, client: &dyn ClientAuthenticator) -> (Acceptor, WampMessage) {
+        let mut acceptor = Acceptor::new(1, Box::new(JSONSerializer {}), vec![server]);
+        let hello = Hello::new(
+            "realm1",
+            &client.authid(),
+            client.auth_extra(),
+            Roles::client().marshal(),
+            vec![client.auth_method()],
+        );
+
+        let challenge = match acceptor.receive_message(WampMessage::Hello(hello)).unwrap() {
+            Some(WampMessage::Challenge(challenge)) => challenge,
+            other => panic!("expected CHALLENGE, got {other:?}"),
+        };
+        assert_eq!(challenge.auth_method, client.auth_method());
+
+        let authenticate = client.authenticate(&challenge).unwrap();
+        let reply = acceptor
+            .receive_message(WampMessage::Authenticate(authenticate))
+            .unwrap()
+            .unwrap();
+
+        (acceptor, reply)
+    }
+
+    fn assert_welcome(acceptor: Acceptor, reply: WampMessage, auth_method: &str) {
+        match reply {
+            WampMessage::Welcome(welcome) => {
+                assert_eq!((welcome.authid.as_str(), welcome.auth_role.as_str()), ("alice", "user"));
+                assert_eq!(welcome.details.get("authmethod"), Some(&Value::str(auth_method)));
+            }
+            other => panic!("expected WELCOME, got {other:?}"),
+        }
+
+        let details = acceptor.into_session_details().unwrap();
+        assert_eq!((details.authid.as_str(), details.auth_role.as_str()), ("alice", "user"));
+    }
+
+    #[test]
+    fn ticket_flow() {
+        let client = TicketAuthenticator::new("alice", "secret", HashMap::new());
+        let (acceptor, reply) = authenticate(Box::new(TicketServerAuthenticator::new(store("secret"))), &client);
+        assert_welcome(acceptor, reply, "ticket");
+
+        let client = TicketAuthenticator::new("alice", "wrong", HashMap::new());
+        let (acceptor, reply) = authenticate(Box::new(TicketServerAuthenticator::new(store("secret"))), &client);
+        assert_eq!(abort_reason(Some(reply)), ERROR_AUTHENTICATION_FAILED);
+        assert!(acceptor.is_aborted());
+    }
+
+    #[test]
+    fn wampcra_flow() {
+        let client = WAMPCRAAuthenticator::new("alice", "secret", HashMap::new());
+        let (acceptor, reply) = authenticate(Box::new(WAMPCRAServerAuthenticator::new(store("secret"))), &client);
+        assert_welcome(acceptor, reply, "wampcra");
+
+        let client = WAMPCRAAuthenticator::new("alice", "wrong", HashMap::new());
+        let (_, reply) = authenticate(Box::new(WAMPCRAServerAuthenticator::new(store("secret"))), &client);
+        assert_eq!(abort_reason(Some(reply)), ERROR_AUTHENTICATION_FAILED);
+    }
+
+    #[test]
+    fn salted_wampcra_flow() {
+        let salted =
+            |request: &AuthRequest| store("secret")(request).map(|credential| credential.with_salt("salt", 100, 32));
+
+        let client = WAMPCRAAuthenticator::new("alice", "secret", HashMap::new());
+        let (acceptor, reply) = authenticate(Box::new(WAMPCRAServerAuthenticator::new(salted)), &client);
+        assert_welcome(acceptor, reply, "wampcra");
+    }
+
+    #[test]
+    fn cryptosign_flow() {
+        let private_key = "4d57d97a68f555696620a6d849c0ce582568518d729eb753dc7c732de2804510";
+        let client = CryptoSignAuthenticator::try_new("alice", private_key, HashMap::new()).unwrap();
+        let public_key = match client.auth_extra().get("pubkey") {
+            Some(Value::Str(public_key)) => public_key.clone(),
+            _ => unreachable!(),
+        };
+
+        let server = CryptoSignServerAuthenticator::new(store(&public_key));
+        let (acceptor, reply) = authenticate(Box::new(server), &client);
+        assert_welcome(acceptor, reply, "cryptosign");
+
+        // a valid signature from a key the store doesn't know
+        let server = CryptoSignServerAuthenticator::new(store(&"00".repeat(32)));
+        let (_, reply) = authenticate(Box::new(server), &client);
+        assert_eq!(abort_reason(Some(reply)), ERROR_AUTHENTICATION_FAILED);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::authenticators::authenticator::ClientAuthenticator;
+use crate::authenticators::authenticator::{
+    AuthOutcome, AuthRequest, AuthResponse, ClientAuthenticator, ServerAuthenticator,
+};
 use crate::messages::authenticate::Authenticate;
 use crate::messages::challenge::Challenge;
 use crate::messages::types::{Error, Value};
@@ -24,7 +26,7 @@ impl ClientAuthenticator for AnonymousAuthenticator {
     }
 
     fn authenticate(&self, _: &Challenge) -> Result<Authenticate, Error> {
-        Err(Error::new(
+        Err(Error::invalid_state(
             "authenticate() must not be called for anonymous authentication",
         ))
     }
@@ -44,3 +46,45 @@ impl Default for AnonymousAuthenticator {
         AnonymousAuthenticator::new("", Default::default())
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct AnonymousServerAuthenticator {
+    auth_role: String,
+}
+
+impl ServerAuthenticator for AnonymousServerAuthenticator {
+    fn auth_method(&self) -> String {
+        "anonymous".to_string()
+    }
+
+    fn hello(&self, request: &AuthRequest) -> Result<AuthOutcome, Error> {
+        // anonymous clients usually don't send an authid, fallback to the session id
+        let authid = if request.authid.is_empty() {
+            request.session_id.to_string()
+        } else {
+            request.authid.clone()
+        };
+
+        Ok(AuthOutcome::Welcome(AuthResponse::new(&authid, &self.auth_role)))
+    }
+
+    fn authenticate(&self, _: &AuthRequest, _: &Challenge, _: &Authenticate) -> Result<AuthResponse, Error> {
+        Err(Error::invalid_state(
+            "authenticate() must not be called for anonymous authentication",
+        ))
+    }
+}
+
+impl AnonymousServerAuthenticator {
+    pub fn new(auth_role: &str) -> Self {
+        AnonymousServerAuthenticator {
+            auth_role: auth_role.to_string(),
+        }
+    }
+}
+
+impl Default for AnonymousServerAuthenticator {
+    fn default() -> Self {
+        AnonymousServerAuthenticator::new("anonymous")
+    }
+}
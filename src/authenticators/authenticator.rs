@@ -29,3 +29,113 @@ impl Clone for Box<dyn ClientAuthenticator> {
         self.clone_box()
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct AuthRequest {
+    pub session_id: i64,
+    pub realm: String,
+    pub authid: String,
+    pub auth_method: String,
+    pub auth_extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthResponse {
+    pub authid: String,
+    pub auth_role: String,
+}
+
+impl AuthResponse {
+    pub fn new(authid: &str, auth_role: &str) -> Self {
+        AuthResponse {
+            authid: authid.to_string(),
+            auth_role: auth_role.to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AuthOutcome {
+    Welcome(AuthResponse),
+    Challenge(Challenge),
+}
+
+pub trait ServerAuthenticator: XServerClone + Debug + Send + Sync {
+    fn auth_method(&self) -> String;
+    fn hello(&self, request: &AuthRequest) -> Result<AuthOutcome, Error>;
+    fn authenticate(
+        &self,
+        request: &AuthRequest,
+        challenge: &Challenge,
+        authenticate: &Authenticate,
+    ) -> Result<AuthResponse, Error>;
+}
+
+pub trait XServerClone {
+    fn clone_box(&self) -> Box<dyn ServerAuthenticator>;
+}
+
+impl<T> XServerClone for T
+where
+    T: ServerAuthenticator + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn ServerAuthenticator> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn ServerAuthenticator> {
+    fn clone(&self) -> Box<dyn ServerAuthenticator> {
+        self.clone_box()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Credential {
+    pub authid: String,
+    pub auth_role: String,
+    pub auth_provider: String,
+    pub secret: String,
+    pub salt: Option<String>,
+    pub iterations: u32,
+    pub keylen: usize,
+}
+
+impl Credential {
+    pub fn new(authid: &str, auth_role: &str, secret: &str) -> Self {
+        Credential {
+            authid: authid.to_string(),
+            auth_role: auth_role.to_string(),
+            auth_provider: "dynamic".to_string(),
+            secret: secret.to_string(),
+            salt: None,
+            iterations: 0,
+            keylen: 0,
+        }
+    }
+
+    pub fn with_auth_provider(mut self, auth_provider: &str) -> Self {
+        self.auth_provider = auth_provider.to_string();
+        self
+    }
+
+    pub fn with_salt(mut self, salt: &str, iterations: u32, keylen: usize) -> Self {
+        self.salt = Some(salt.to_string());
+        self.iterations = iterations;
+        self.keylen = keylen;
+        self
+    }
+}
+
+pub trait CredentialStore: Send + Sync {
+    fn lookup(&self, request: &AuthRequest) -> Result<Credential, Error>;
+}
+
+impl<F> CredentialStore for F
+where
+    F: Fn(&AuthRequest) -> Result<Credential, Error> + Send + Sync,
+{
+    fn lookup(&self, request: &AuthRequest) -> Result<Credential, Error> {
+        self(request)
+    }
+}
@@ -1,10 +1,14 @@
-use crate::authenticators::authenticator::ClientAuthenticator;
+use crate::authenticators::authenticator::{
+    AuthOutcome, AuthRequest, AuthResponse, ClientAuthenticator, CredentialStore, ServerAuthenticator,
+};
 use crate::messages::authenticate::Authenticate;
 use crate::messages::challenge::Challenge;
 use crate::messages::types::{Error, Value};
-use ed25519_dalek::{Signer, SigningKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use hex::{FromHex, ToHex};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct CryptoSignAuthenticator {
@@ -33,10 +37,10 @@ impl ClientAuthenticator for CryptoSignAuthenticator {
                     signature: signed,
                     extra: HashMap::new(),
                 }),
-                Err(err) => Err(Error::new(format!("failed to sign challenge: {err}"))),
+                Err(err) => Err(Error::authentication(format!("failed to sign challenge: {err}"))),
             }
         } else {
-            Err(Error::new("challenge missing in authextra or is none"))
+            Err(Error::authentication("challenge missing in authextra or is none"))
         }
     }
 }
@@ -54,15 +58,83 @@ impl CryptoSignAuthenticator {
                     extra: cloned,
                 })
             }
-            Err(err) => Err(Error::new(format!("{err}"))),
+            Err(err) => Err(Error::authentication(format!("{err}"))),
         }
     }
 }
 
+#[derive(Clone)]
+pub struct CryptoSignServerAuthenticator {
+    store: Arc<dyn CredentialStore>,
+}
+
+impl fmt::Debug for CryptoSignServerAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CryptoSignServerAuthenticator").finish_non_exhaustive()
+    }
+}
+
+impl ServerAuthenticator for CryptoSignServerAuthenticator {
+    fn auth_method(&self) -> String {
+        "cryptosign".to_string()
+    }
+
+    fn hello(&self, request: &AuthRequest) -> Result<AuthOutcome, Error> {
+        if !matches!(request.auth_extra.get("pubkey"), Some(Value::Str(_))) {
+            return Err(Error::authentication("pubkey missing in authextra or is not a string"));
+        }
+
+        let mut extra = HashMap::new();
+        extra.insert("challenge".to_string(), Value::Str(generate_crypto_sign_challenge()));
+
+        Ok(AuthOutcome::Challenge(Challenge {
+            auth_method: self.auth_method(),
+            extra,
+        }))
+    }
+
+    fn authenticate(
+        &self,
+        request: &AuthRequest,
+        challenge: &Challenge,
+        authenticate: &Authenticate,
+    ) -> Result<AuthResponse, Error> {
+        let public_key = match request.auth_extra.get("pubkey") {
+            Some(Value::Str(s)) => s,
+            _ => return Err(Error::authentication("pubkey missing in authextra or is not a string")),
+        };
+
+        let challenge_hex = match challenge.extra.get("challenge") {
+            Some(Value::Str(s)) => s,
+            _ => return Err(Error::authentication("challenge missing in authextra or is none")),
+        };
+
+        // the store decides which public key is authorized for the authid
+        let credential = self.store.lookup(request)?;
+        if !credential.secret.eq_ignore_ascii_case(public_key) {
+            return Err(Error::authentication("public key is not authorized"));
+        }
+
+        verify_crypto_sign_signature(&authenticate.signature, challenge_hex, public_key)?;
+        Ok(AuthResponse::new(&credential.authid, &credential.auth_role))
+    }
+}
+
+impl CryptoSignServerAuthenticator {
+    pub fn new<S: CredentialStore + 'static>(store: S) -> Self {
+        CryptoSignServerAuthenticator { store: Arc::new(store) }
+    }
+}
+
+pub fn generate_crypto_sign_challenge() -> String {
+    let challenge: [u8; 32] = rand::random();
+    hex::encode(challenge)
+}
+
 fn signing_key_from_hex(hex_str: &str) -> Result<SigningKey, Error> {
     match <[u8; 32]>::from_hex(hex_str) {
         Ok(bytes) => Ok(SigningKey::from_bytes(&bytes)),
-        Err(e) => Err(Error::new(format!("invalid hex or wrong length: {e}"))),
+        Err(e) => Err(Error::authentication(format!("invalid hex or wrong length: {e}"))),
     }
 }
 
@@ -74,6 +146,32 @@ fn sign_crypto_sign_challenge(challenge: &str, private_key: &SigningKey) -> Resu
 
             Ok(format!("{signed_hex}{challenge}"))
         }
-        Err(e) => Err(Error::new(format!("failed to decode challenge hex: {e}"))),
+        Err(e) => Err(Error::authentication(format!("failed to decode challenge hex: {e}"))),
+    }
+}
+
+fn verify_crypto_sign_signature(signature: &str, challenge: &str, public_key: &str) -> Result<(), Error> {
+    let signature_raw =
+        hex::decode(signature).map_err(|e| Error::authentication(format!("failed to decode signature hex: {e}")))?;
+    if signature_raw.len() < Signature::BYTE_SIZE {
+        return Err(Error::authentication("signature is too short"));
     }
+
+    let (signature_raw, signed_challenge) = signature_raw.split_at(Signature::BYTE_SIZE);
+    let challenge_raw =
+        hex::decode(challenge).map_err(|e| Error::authentication(format!("failed to decode challenge hex: {e}")))?;
+    if signed_challenge != challenge_raw.as_slice() {
+        return Err(Error::authentication("signature does not contain the challenge"));
+    }
+
+    let key_bytes = <[u8; 32]>::from_hex(public_key)
+        .map_err(|e| Error::authentication(format!("invalid hex or wrong length: {e}")))?;
+    let verifying_key =
+        VerifyingKey::from_bytes(&key_bytes).map_err(|e| Error::authentication(format!("invalid public key: {e}")))?;
+
+    let signature =
+        Signature::from_slice(signature_raw).map_err(|e| Error::authentication(format!("invalid signature: {e}")))?;
+    verifying_key
+        .verify(&challenge_raw, &signature)
+        .map_err(|_| Error::authentication("invalid signature"))
 }
@@ -1,8 +1,13 @@
-use crate::authenticators::authenticator::ClientAuthenticator;
+use crate::authenticators::authenticator::{
+    AuthOutcome, AuthRequest, AuthResponse, ClientAuthenticator, CredentialStore, ServerAuthenticator,
+};
 use crate::messages::authenticate::Authenticate;
 use crate::messages::challenge::Challenge;
 use crate::messages::types::{Error, Value};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
 
 #[derive(Debug, Clone)]
 pub struct TicketAuthenticator {
@@ -41,3 +46,47 @@ impl TicketAuthenticator {
         }
     }
 }
+
+#[derive(Clone)]
+pub struct TicketServerAuthenticator {
+    store: Arc<dyn CredentialStore>,
+}
+
+impl fmt::Debug for TicketServerAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TicketServerAuthenticator").finish_non_exhaustive()
+    }
+}
+
+impl ServerAuthenticator for TicketServerAuthenticator {
+    fn auth_method(&self) -> String {
+        "ticket".to_string()
+    }
+
+    fn hello(&self, _: &AuthRequest) -> Result<AuthOutcome, Error> {
+        Ok(AuthOutcome::Challenge(Challenge {
+            auth_method: self.auth_method(),
+            extra: HashMap::new(),
+        }))
+    }
+
+    fn authenticate(
+        &self,
+        request: &AuthRequest,
+        _: &Challenge,
+        authenticate: &Authenticate,
+    ) -> Result<AuthResponse, Error> {
+        let credential = self.store.lookup(request)?;
+        if !bool::from(credential.secret.as_bytes().ct_eq(authenticate.signature.as_bytes())) {
+            return Err(Error::authentication("invalid ticket"));
+        }
+
+        Ok(AuthResponse::new(&credential.authid, &credential.auth_role))
+    }
+}
+
+impl TicketServerAuthenticator {
+    pub fn new<S: CredentialStore + 'static>(store: S) -> Self {
+        TicketServerAuthenticator { store: Arc::new(store) }
+    }
+}
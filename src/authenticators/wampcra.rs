@@ -1,4 +1,6 @@
-use crate::authenticators::authenticator::ClientAuthenticator;
+use crate::authenticators::authenticator::{
+    AuthOutcome, AuthRequest, AuthResponse, ClientAuthenticator, Credential, CredentialStore, ServerAuthenticator,
+};
 use crate::messages::authenticate::Authenticate;
 use crate::messages::challenge::Challenge;
 use crate::messages::types::{Error, Value};
@@ -7,6 +9,8 @@ use hmac::{Hmac, Mac};
 use pbkdf2::pbkdf2_hmac;
 use sha2::Sha256;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct WAMPCRAAuthenticator {
@@ -31,7 +35,7 @@ impl ClientAuthenticator for WAMPCRAAuthenticator {
     fn authenticate(&self, challenge: &Challenge) -> Result<Authenticate, Error> {
         let challenge_hex = match challenge.extra.get("challenge") {
             Some(Value::Str(s)) => s.to_string(),
-            _ => return Err(Error::new("challenge must be a string")),
+            _ => return Err(Error::authentication("challenge must be a string")),
         };
 
         if challenge.extra.contains_key("salt")
@@ -40,32 +44,32 @@ impl ClientAuthenticator for WAMPCRAAuthenticator {
         {
             let salt = match challenge.extra.get("salt") {
                 Some(Value::Str(s)) => s.to_string(),
-                _ => return Err(Error::new("salt must be a string")),
+                _ => return Err(Error::authentication("salt must be a string")),
             };
 
             let iterations = match challenge.extra.get("iterations") {
                 Some(Value::Int(s)) => s,
-                _ => return Err(Error::new("iterations must be an int")),
+                _ => return Err(Error::authentication("iterations must be an int")),
             };
 
             let keylen = match challenge.extra.get("keylen") {
                 Some(Value::Int(s)) => s,
-                _ => return Err(Error::new("keylen must be an int")),
+                _ => return Err(Error::authentication("keylen must be an int")),
             };
 
             let iterations: u32 = (*iterations)
                 .try_into()
-                .map_err(|_| Error::new("Invalid value for iterations: must be positive"))?;
+                .map_err(|_| Error::authentication("Invalid value for iterations: must be positive"))?;
 
             let keylen: usize = (*keylen)
                 .try_into()
-                .map_err(|_| Error::new("Invalid value for keylen: must be positive"))?;
+                .map_err(|_| Error::authentication("Invalid value for keylen: must be positive"))?;
 
             let key = derive_wamp_cra_key(self.secret.as_str(), salt.as_str(), iterations, keylen)
-                .map_err(|e| Error::new(e.to_string()))?;
+                .map_err(|e| Error::authentication(e.to_string()))?;
 
-            let signature =
-                sign_cra_challenge(challenge_hex.as_str(), key.as_bytes()).map_err(|e| Error::new(e.to_string()))?;
+            let signature = sign_cra_challenge(challenge_hex.as_str(), key.as_bytes())
+                .map_err(|e| Error::authentication(e.to_string()))?;
 
             Ok(Authenticate {
                 signature,
@@ -73,7 +77,7 @@ impl ClientAuthenticator for WAMPCRAAuthenticator {
             })
         } else {
             let signature = sign_cra_challenge(challenge_hex.as_str(), self.secret.as_bytes())
-                .map_err(|e| Error::new(e.to_string()))?;
+                .map_err(|e| Error::authentication(e.to_string()))?;
 
             Ok(Authenticate {
                 signature,
@@ -93,6 +97,77 @@ impl WAMPCRAAuthenticator {
     }
 }
 
+#[derive(Clone)]
+pub struct WAMPCRAServerAuthenticator {
+    store: Arc<dyn CredentialStore>,
+}
+
+impl fmt::Debug for WAMPCRAServerAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WAMPCRAServerAuthenticator").finish_non_exhaustive()
+    }
+}
+
+impl ServerAuthenticator for WAMPCRAServerAuthenticator {
+    fn auth_method(&self) -> String {
+        "wampcra".to_string()
+    }
+
+    fn hello(&self, request: &AuthRequest) -> Result<AuthOutcome, Error> {
+        let credential = self.store.lookup(request)?;
+        let challenge = generate_wamp_cra_challenge(
+            request.session_id,
+            &credential.authid,
+            &credential.auth_role,
+            &credential.auth_provider,
+        )?;
+
+        let mut extra = HashMap::new();
+        extra.insert("challenge".to_string(), Value::Str(challenge));
+        if let Some(salt) = &credential.salt {
+            extra.insert("salt".to_string(), Value::Str(salt.clone()));
+            extra.insert("iterations".to_string(), Value::Int(credential.iterations.into()));
+            extra.insert("keylen".to_string(), Value::Int(credential.keylen as i64));
+        }
+
+        Ok(AuthOutcome::Challenge(Challenge {
+            auth_method: self.auth_method(),
+            extra,
+        }))
+    }
+
+    fn authenticate(
+        &self,
+        request: &AuthRequest,
+        challenge: &Challenge,
+        authenticate: &Authenticate,
+    ) -> Result<AuthResponse, Error> {
+        let challenge_str = match challenge.extra.get("challenge") {
+            Some(Value::Str(s)) => s,
+            _ => return Err(Error::authentication("challenge must be a string")),
+        };
+
+        let credential = self.store.lookup(request)?;
+        let key = wamp_cra_key(&credential)?;
+        verify_cra_signature(challenge_str, key.as_bytes(), &authenticate.signature)?;
+
+        Ok(AuthResponse::new(&credential.authid, &credential.auth_role))
+    }
+}
+
+impl WAMPCRAServerAuthenticator {
+    pub fn new<S: CredentialStore + 'static>(store: S) -> Self {
+        WAMPCRAServerAuthenticator { store: Arc::new(store) }
+    }
+}
+
+fn wamp_cra_key(credential: &Credential) -> Result<String, Error> {
+    match &credential.salt {
+        Some(salt) => derive_wamp_cra_key(&credential.secret, salt, credential.iterations, credential.keylen),
+        None => Ok(credential.secret.clone()),
+    }
+}
+
 // Type alias for HMAC-SHA256
 type HmacSha256 = Hmac<Sha256>;
 
@@ -103,10 +178,42 @@ fn sign_cra_challenge(challenge: &str, key: &[u8]) -> Result<String, Error> {
             let signature = mac.finalize().into_bytes();
             Ok(general_purpose::STANDARD.encode(signature))
         }
-        Err(err) => Err(Error::new(format!("Failed to generate mac {err}"))),
+        Err(err) => Err(Error::authentication(format!("Failed to generate mac {err}"))),
     }
 }
 
+fn verify_cra_signature(challenge: &str, key: &[u8], signature: &str) -> Result<(), Error> {
+    let signature = general_purpose::STANDARD
+        .decode(signature)
+        .map_err(|e| Error::authentication(format!("failed to decode signature: {e}")))?;
+
+    let mut mac =
+        HmacSha256::new_from_slice(key).map_err(|e| Error::authentication(format!("Failed to generate mac {e}")))?;
+    mac.update(challenge.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| Error::authentication("invalid signature"))
+}
+
+pub fn generate_wamp_cra_challenge(
+    session_id: i64,
+    authid: &str,
+    auth_role: &str,
+    provider: &str,
+) -> Result<String, Error> {
+    let nonce: [u8; 16] = rand::random();
+
+    let challenge = serde_json::json!({
+        "nonce": hex::encode(nonce),
+        "authprovider": provider,
+        "authid": authid,
+        "authrole": auth_role,
+        "authmethod": "wampcra",
+        "session": session_id,
+    });
+
+    serde_json::to_string(&challenge).map_err(|e| Error::authentication(format!("failed to encode challenge: {e}")))
+}
+
 pub fn derive_wamp_cra_key(secret: &str, salt: &str, iterations: u32, keylen: usize) -> Result<String, Error> {
     let mut derived_key = vec![0u8; keylen];
 
@@ -0,0 +1,419 @@
+use crate::idgen::{GlobalScopeIDGenerator, RouterScopeIDGenerator};
+use crate::matcher::UriMatcher;
+use crate::messages::error::Error as ErrorMessage;
+use crate::messages::event::Event;
+use crate::messages::message::Message;
+use crate::messages::options::{DETAIL_TOPIC, MatchPolicy, PublishOptions, SubscribeOptions};
+use crate::messages::passthru::PassthruOptions;
+use crate::messages::publish::{MESSAGE_TYPE_PUBLISH, Publish};
+use crate::messages::published::Published;
+use crate::messages::subscribe::{MESSAGE_TYPE_SUBSCRIBE, Subscribe};
+use crate::messages::subscribed::Subscribed;
+use crate::messages::types::{Error, Value};
+use crate::messages::unsubscribe::{MESSAGE_TYPE_UNSUBSCRIBE, Unsubscribe};
+use crate::messages::unsubscribed::Unsubscribed;
+use crate::messages::wamp_message::WampMessage;
+use crate::types::{MessageWithRecipient, SessionDetails};
+use std::collections::{HashMap, HashSet};
+
+const ERROR_NO_SUCH_SUBSCRIPTION: &str = "wamp.error.no_such_subscription";
+
+#[derive(Debug)]
+pub struct Subscription {
+    pub id: i64,
+    pub topic: String,
+    pub match_policy: MatchPolicy,
+    pub subscribers: Vec<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Broker {
+    subscriptions: HashMap<i64, Subscription>,
+    subscriptions_by_topic: UriMatcher<i64>,
+    subscriptions_by_session: HashMap<i64, HashSet<i64>>,
+    // the authid and authrole of the sessions are matched against the black- and whitelists of publications
+    sessions: HashMap<i64, SessionDetails>,
+
+    subscription_idgen: RouterScopeIDGenerator,
+    publication_idgen: GlobalScopeIDGenerator,
+}
+
+impl Broker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_session(&mut self, details: &SessionDetails) -> Result<(), Error> {
+        if self.subscriptions_by_session.contains_key(&details.id) {
+            return Err(Error::invalid_state("cannot add session twice"));
+        }
+
+        self.subscriptions_by_session.insert(details.id, HashSet::new());
+        self.sessions.insert(details.id, details.clone());
+        Ok(())
+    }
+
+    pub fn remove_session(&mut self, session_id: i64) {
+        self.sessions.remove(&session_id);
+        if let Some(subscription_ids) = self.subscriptions_by_session.remove(&session_id) {
+            for subscription_id in subscription_ids {
+                self.remove_subscriber(subscription_id, session_id);
+            }
+        }
+    }
+
+    // whether an event published to the topic would reach any subscription
+    pub fn has_subscription(&self, topic: &str) -> bool {
+        !self.subscriptions_by_topic.matches(topic).is_empty()
+    }
+
+    pub fn subscription(&self, subscription_id: i64) -> Option<&Subscription> {
+        self.subscriptions.get(&subscription_id)
+    }
+
+    pub fn subscriptions(&self) -> impl Iterator<Item = &Subscription> {
+        self.subscriptions.values()
+    }
+
+    pub fn receive_message(&mut self, session_id: i64, msg: WampMessage) -> Result<Vec<MessageWithRecipient>, Error> {
+        match msg {
+            WampMessage::Subscribe(subscribe) => Ok(vec![self.receive_subscribe(session_id, &subscribe)?]),
+
+            WampMessage::Unsubscribe(unsubscribe) => Ok(vec![self.receive_unsubscribe(session_id, &unsubscribe)]),
+
+            WampMessage::Publish(publish) => self.receive_publish(session_id, &publish),
+
+            _ => Err(Error::protocol_violation(format!(
+                "broker: received unexpected message of type {:?}",
+                msg.message_type()
+            ))),
+        }
+    }
+
+    fn receive_subscribe(&mut self, session_id: i64, subscribe: &Subscribe) -> Result<MessageWithRecipient, Error> {
+        let session_subscriptions = match self.subscriptions_by_session.get_mut(&session_id) {
+            Some(subscriptions) => subscriptions,
+            None => return Err(Error::invalid_state("cannot subscribe, session doesn't exist")),
+        };
+
+        let match_policy = match SubscribeOptions::parse(&subscribe.options) {
+            Ok(options) => options.match_policy.unwrap_or_default(),
+            Err(e) => {
+                return Ok(MessageWithRecipient::new(
+                    e.to_error_message(MESSAGE_TYPE_SUBSCRIBE, subscribe.request_id),
+                    session_id,
+                ));
+            }
+        };
+
+        let subscription_id = match self.subscriptions_by_topic.get(match_policy, &subscribe.topic) {
+            Some(subscription_id) => *subscription_id,
+            None => {
+                let subscription_id = self.subscription_idgen.next_id();
+                self.subscriptions_by_topic
+                    .insert(match_policy, &subscribe.topic, subscription_id);
+                self.subscriptions.insert(
+                    subscription_id,
+                    Subscription {
+                        id: subscription_id,
+                        topic: subscribe.topic.clone(),
+                        match_policy,
+                        subscribers: Vec::new(),
+                    },
+                );
+
+                subscription_id
+            }
+        };
+
+        // subscribing to the same topic twice yields the existing subscription
+        if session_subscriptions.insert(subscription_id) {
+            let subscription = self.subscriptions.get_mut(&subscription_id).unwrap();
+            subscription.subscribers.push(session_id);
+        }
+
+        let subscribed = Subscribed {
+            request_id: subscribe.request_id,
+            subscription_id,
+        };
+
+        Ok(MessageWithRecipient::new(subscribed, session_id))
+    }
+
+    fn receive_unsubscribe(&mut self, session_id: i64, unsubscribe: &Unsubscribe) -> MessageWithRecipient {
+        let removed = match self.subscriptions_by_session.get_mut(&session_id) {
+            Some(subscriptions) => subscriptions.remove(&unsubscribe.subscription_id),
+            None => false,
+        };
+
+        if !removed {
+            let error = ErrorMessage {
+                message_type: MESSAGE_TYPE_UNSUBSCRIBE,
+                request_id: unsubscribe.request_id,
+                options: HashMap::new(),
+                uri: ERROR_NO_SUCH_SUBSCRIPTION.to_string(),
+                args: None,
+                kwargs: None,
+            };
+
+            return MessageWithRecipient::new(error, session_id);
+        }
+
+        self.remove_subscriber(unsubscribe.subscription_id, session_id);
+        let unsubscribed = Unsubscribed {
+            request_id: unsubscribe.request_id,
+        };
+
+        MessageWithRecipient::new(unsubscribed, session_id)
+    }
+
+    fn receive_publish(&mut self, session_id: i64, publish: &Publish) -> Result<Vec<MessageWithRecipient>, Error> {
+        if !self.subscriptions_by_session.contains_key(&session_id) {
+            return Err(Error::invalid_state("cannot publish, session doesn't exist"));
+        }
+
+        let options = match PublishOptions::parse(&publish.options) {
+            Ok(options) => options,
+            Err(e) => {
+                return Ok(vec![MessageWithRecipient::new(
+                    e.to_error_message(MESSAGE_TYPE_PUBLISH, publish.request_id),
+                    session_id,
+                )]);
+            }
+        };
+
+        let publication_id = self.publication_idgen.next_id();
+        let exclude_me = options.excludes_publisher();
+
+        let mut messages = Vec::new();
+        for subscription in self
+            .subscriptions_by_topic
+            .matches(&publish.topic)
+            .into_iter()
+            .filter_map(|id| self.subscriptions.get(id))
+        {
+            for subscriber in &subscription.subscribers {
+                if exclude_me && *subscriber == session_id {
+                    continue;
+                }
+
+                let details = &self.sessions[subscriber];
+                if !options.admits(details.id, &details.authid, &details.auth_role) {
+                    continue;
+                }
+
+                let mut details = HashMap::new();
+                PassthruOptions::forward(&publish.options, &mut details);
+                // subscribers of a pattern need to know which topic the event was actually published to
+                if subscription.match_policy != MatchPolicy::Exact {
+                    details.insert(DETAIL_TOPIC.to_string(), Value::str(publish.topic.clone()));
+                }
+
+                let event = Event {
+                    subscription_id: subscription.id,
+                    publication_id,
+                    details,
+                    args: publish.args.clone(),
+                    kwargs: publish.kwargs.clone(),
+                };
+
+                messages.push(MessageWithRecipient::new(event, *subscriber));
+            }
+        }
+
+        if options.acknowledge == Some(true) {
+            let published = Published {
+                request_id: publish.request_id,
+                publication_id,
+            };
+
+            messages.push(MessageWithRecipient::new(published, session_id));
+        }
+
+        Ok(messages)
+    }
+
+    fn remove_subscriber(&mut self, subscription_id: i64, session_id: i64) {
+        if let Some(subscription) = self.subscriptions.get_mut(&subscription_id) {
+            subscription.subscribers.retain(|subscriber| *subscriber != session_id);
+            if subscription.subscribers.is_empty() {
+                self.subscriptions_by_topic
+                    .remove(subscription.match_policy, &subscription.topic);
+                self.subscriptions.remove(&subscription_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sessions 1 to 4 subscribe to "foo.bar", sessions 1 and 2 are alice, odd sessions are admins
+    fn broker() -> (Broker, i64) {
+        let mut broker = Broker::new();
+        let mut subscription_id = 0;
+        for id in 1..=4 {
+            let authid = if id <= 2 { "alice" } else { "bob" };
+            let auth_role = if id % 2 == 1 { "admin" } else { "user" };
+            let details = SessionDetails::new(
+                id,
+                "realm1".to_string(),
+                authid.to_string(),
+                auth_role.to_string(),
+                false,
+            );
+            broker.add_session(&details).unwrap();
+
+            let subscribe = Subscribe {
+                request_id: 1,
+                options: HashMap::new(),
+                topic: "foo.bar".to_string(),
+            };
+            match broker.receive_message(id, subscribe.into()).unwrap().remove(0).message {
+                WampMessage::Subscribed(subscribed) => subscription_id = subscribed.subscription_id,
+                other => panic!("expected SUBSCRIBED, got {other:?}"),
+            }
+        }
+
+        (broker, subscription_id)
+    }
+
+    fn publish(broker: &mut Broker, session_id: i64, options: PublishOptions) -> Vec<i64> {
+        let publish = Publish {
+            request_id: 7,
+            options: options.marshal(),
+            topic: "foo.bar".to_string(),
+            args: None,
+            kwargs: None,
+        };
+
+        broker
+            .receive_message(session_id, publish.into())
+            .unwrap()
+            .into_iter()
+            .map(|m| m.recipient)
+            .collect()
+    }
+
+    #[test]
+    fn publish_event() {
+        let (mut broker, subscription_id) = broker();
+        let publish = Publish {
+            request_id: 7,
+            options: PublishOptions::new().with_acknowledge(true).marshal(),
+            topic: "foo.bar".to_string(),
+            args: Some(vec![Value::Int(1)]),
+            kwargs: None,
+        };
+
+        let mut messages = broker.receive_message(1, publish.into()).unwrap();
+        assert_eq!(messages.len(), 4);
+
+        let published = messages.pop().unwrap();
+        let publication_id = match published.message {
+            WampMessage::Published(published) => published.publication_id,
+            other => panic!("expected PUBLISHED, got {other:?}"),
+        };
+        assert_eq!(published.recipient, 1);
+
+        for (message, recipient) in messages.into_iter().zip(2..) {
+            let event = Event {
+                subscription_id,
+                publication_id,
+                details: HashMap::new(),
+                args: Some(vec![Value::Int(1)]),
+                kwargs: None,
+            };
+            assert_eq!(message.recipient, recipient);
+            assert_eq!(message.message.marshal(), event.marshal());
+        }
+    }
+
+    #[test]
+    fn exclude_me() {
+        let (mut broker, _) = broker();
+        assert_eq!(publish(&mut broker, 1, PublishOptions::new()), vec![2, 3, 4]);
+        assert_eq!(
+            publish(&mut broker, 1, PublishOptions::new().with_exclude_me(false)),
+            vec![1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn blacklists() {
+        let (mut broker, _) = broker();
+        let options = PublishOptions::new().with_exclude_me(false);
+        assert_eq!(
+            publish(&mut broker, 1, options.clone().with_exclude(vec![2, 4])),
+            vec![1, 3]
+        );
+        assert_eq!(
+            publish(
+                &mut broker,
+                1,
+                options.clone().with_exclude_authid(vec!["alice".to_string()])
+            ),
+            vec![3, 4]
+        );
+        assert_eq!(
+            publish(&mut broker, 1, options.with_exclude_authrole(vec!["admin".to_string()])),
+            vec![2, 4]
+        );
+    }
+
+    #[test]
+    fn whitelists() {
+        let (mut broker, _) = broker();
+        let options = PublishOptions::new().with_exclude_me(false);
+        assert_eq!(
+            publish(&mut broker, 1, options.clone().with_eligible(vec![1, 4])),
+            vec![1, 4]
+        );
+        assert_eq!(
+            publish(
+                &mut broker,
+                1,
+                options.clone().with_eligible_authid(vec!["bob".to_string()])
+            ),
+            vec![3, 4]
+        );
+        assert_eq!(
+            publish(
+                &mut broker,
+                1,
+                options.clone().with_eligible_authrole(vec!["user".to_string()])
+            ),
+            vec![2, 4]
+        );
+
+        // every list has to admit the subscriber
+        let options = options
+            .with_eligible_authid(vec!["alice".to_string()])
+            .with_eligible_authrole(vec!["admin".to_string()])
+            .with_exclude(vec![1]);
+        assert!(publish(&mut broker, 1, options).is_empty());
+    }
+
+    #[test]
+    fn unsubscribe() {
+        let (mut broker, subscription_id) = broker();
+        let unsubscribe = |request_id| Unsubscribe {
+            request_id,
+            subscription_id,
+        };
+
+        let reply = broker.receive_message(2, unsubscribe(2).into()).unwrap().remove(0);
+        assert_eq!(reply.message.marshal(), Unsubscribed { request_id: 2 }.marshal());
+        assert_eq!(publish(&mut broker, 1, PublishOptions::new()), vec![3, 4]);
+
+        let reply = broker.receive_message(2, unsubscribe(3).into()).unwrap().remove(0);
+        match reply.message {
+            WampMessage::Error(error) => assert_eq!(error.uri, ERROR_NO_SUCH_SUBSCRIPTION),
+            other => panic!("expected ERROR, got {other:?}"),
+        }
+
+        broker.remove_session(3);
+        assert_eq!(publish(&mut broker, 1, PublishOptions::new()), vec![4]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// monotonic time source, only differences between two readings are meaningful
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+#[derive(Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// a clock that only moves when told to, for driving timeouts from an event loop's own timer or in tests
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    millis: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.millis.store(now.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_millis(self.millis.load(Ordering::SeqCst))
+    }
+}
+
+#[derive(Debug)]
+pub struct DeadlineTracker<K> {
+    clock: Arc<dyn Clock>,
+    deadlines: HashMap<K, Duration>,
+}
+
+impl<K> Default for DeadlineTracker<K> {
+    fn default() -> Self {
+        DeadlineTracker::new(Arc::new(SystemClock::new()))
+    }
+}
+
+impl<K> DeadlineTracker<K> {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        DeadlineTracker {
+            clock,
+            deadlines: HashMap::new(),
+        }
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    // switches to another clock, running deadlines keep the time they had left
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let (old_now, new_now) = (self.clock.now(), clock.now());
+        for deadline in self.deadlines.values_mut() {
+            *deadline = new_now + deadline.saturating_sub(old_now);
+        }
+
+        self.clock = clock;
+    }
+
+    pub fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+
+    // time left until the earliest deadline, zero if one has already passed
+    pub fn next_timeout(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.deadlines
+            .values()
+            .min()
+            .map(|deadline| deadline.saturating_sub(now))
+    }
+}
+
+impl<K: Copy + Eq + Hash> DeadlineTracker<K> {
+    pub fn start(&mut self, key: K, timeout: Duration) {
+        self.deadlines.insert(key, self.clock.now() + timeout);
+    }
+
+    pub fn stop(&mut self, key: &K) -> bool {
+        self.deadlines.remove(key).is_some()
+    }
+
+    // removes and returns every key whose deadline has passed
+    pub fn expired(&mut self) -> Vec<K> {
+        let now = self.clock.now();
+        let expired: Vec<K> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(key, _)| *key)
+            .collect();
+
+        for key in &expired {
+            self.deadlines.remove(key);
+        }
+
+        expired
+    }
+}
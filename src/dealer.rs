@@ -0,0 +1,1355 @@
+use crate::clock::{Clock, DeadlineTracker};
+use crate::idgen::{AtomicSessionScopeIDGenerator, RouterScopeIDGenerator};
+use crate::matcher::UriMatcher;
+use crate::messages::call::{Call, MESSAGE_TYPE_CALL};
+use crate::messages::cancel::Cancel;
+use crate::messages::error::Error as ErrorMessage;
+use crate::messages::interrupt::Interrupt;
+use crate::messages::invocation::{Invocation, MESSAGE_TYPE_INVOCATION};
+use crate::messages::message::Message;
+use crate::messages::options::{
+    CallOptions, CancelMode, CancelOptions, DETAIL_PROCEDURE, InvokePolicy, MatchPolicy, OPTION_PROGRESS,
+    OPTION_REASON, OPTION_RECEIVE_PROGRESS, RegisterOptions, YieldOptions,
+};
+use crate::messages::passthru::PassthruOptions;
+use crate::messages::register::{MESSAGE_TYPE_REGISTER, Register};
+use crate::messages::registered::Registered;
+use crate::messages::result::Result_;
+use crate::messages::types::{Error, Value};
+use crate::messages::unregister::{MESSAGE_TYPE_UNREGISTER, Unregister};
+use crate::messages::unregistered::Unregistered;
+use crate::messages::wamp_message::WampMessage;
+use crate::messages::yield_::Yield;
+use crate::roles::ROLE_CALLEE;
+use crate::types::{MessageWithRecipient, SessionDetails};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+const ERROR_NO_SUCH_PROCEDURE: &str = "wamp.error.no_such_procedure";
+const ERROR_PROCEDURE_ALREADY_EXISTS: &str = "wamp.error.procedure_already_exists";
+const ERROR_NO_SUCH_REGISTRATION: &str = "wamp.error.no_such_registration";
+const ERROR_CANCELED: &str = "wamp.error.canceled";
+const ERROR_TIMEOUT: &str = "wamp.error.timeout";
+
+// how many invocations whose late reply is dropped are remembered, the oldest are forgotten first
+const MAX_ORPHANED_CALLS: usize = 4096;
+
+#[derive(Debug)]
+struct PendingInvocation {
+    request_id: i64,
+    registration_id: i64,
+    caller_id: i64,
+    callee_id: i64,
+    receive_progress: bool,
+    // the caller is still sending progressive CALLs for this invocation
+    progressive: bool,
+    // canceled with kill, the caller waits for the interrupted callee to reply
+    interrupted: bool,
+}
+
+#[derive(Debug)]
+pub struct Registration {
+    pub id: i64,
+    pub procedure: String,
+    pub match_policy: MatchPolicy,
+    pub invoke_policy: InvokePolicy,
+    // in the order the callees registered
+    pub registrants: Vec<i64>,
+    round_robin: usize,
+}
+
+impl Registration {
+    // picks the callee to invoke according to the invoke policy
+    fn next_callee(&mut self, rng: &mut InvokeRng) -> i64 {
+        let index = match self.invoke_policy {
+            InvokePolicy::Single | InvokePolicy::First => 0,
+            InvokePolicy::Last => self.registrants.len() - 1,
+            InvokePolicy::RoundRobin => {
+                let index = self.round_robin % self.registrants.len();
+                self.round_robin = index + 1;
+                index
+            }
+            InvokePolicy::Random => rng.0.random_range(0..self.registrants.len()),
+        };
+
+        self.registrants[index]
+    }
+}
+
+// source of the random invoke policy, seeded from the OS unless Dealer::with_seed is used
+#[derive(Debug)]
+struct InvokeRng(StdRng);
+
+impl Default for InvokeRng {
+    fn default() -> Self {
+        InvokeRng(StdRng::from_os_rng())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Dealer {
+    registrations: HashMap<i64, Registration>,
+    registrations_by_procedure: UriMatcher<i64>,
+    registrations_by_session: HashMap<i64, HashSet<i64>>,
+    pending_calls: HashMap<i64, PendingInvocation>,
+    // (caller session, caller request_id) -> invocation request_id
+    pending_calls_by_caller: HashMap<(i64, i64), i64>,
+    call_timeouts: DeadlineTracker<i64>,
+    // (callee session, invocation request_id) of invocations whose caller has left
+    orphaned_calls: HashSet<(i64, i64)>,
+    orphaned_order: VecDeque<(i64, i64)>,
+    // sessions whose callee role announced call_canceling, only those are sent INTERRUPT
+    canceling_callees: HashSet<i64>,
+
+    registration_idgen: RouterScopeIDGenerator,
+    request_idgen: AtomicSessionScopeIDGenerator,
+    invoke_rng: InvokeRng,
+}
+
+impl Dealer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.call_timeouts.set_clock(clock);
+    }
+
+    // makes the callees picked for the random invoke policy reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.invoke_rng = InvokeRng(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    pub fn add_session(&mut self, details: &SessionDetails) -> Result<(), Error> {
+        if self.registrations_by_session.contains_key(&details.id) {
+            return Err(Error::invalid_state("cannot add session twice"));
+        }
+
+        self.registrations_by_session.insert(details.id, HashSet::new());
+        if details.roles.supports(ROLE_CALLEE, |features| features.call_canceling) {
+            self.canceling_callees.insert(details.id);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_session(&mut self, session_id: i64) -> Vec<MessageWithRecipient> {
+        if let Some(registration_ids) = self.registrations_by_session.remove(&session_id) {
+            for registration_id in registration_ids {
+                self.remove_registrant(registration_id, session_id);
+            }
+        }
+
+        self.canceling_callees.remove(&session_id);
+
+        // calls made by the removed session are killed, calls it was serving fail for their callers
+        let request_ids: Vec<i64> = self
+            .pending_calls
+            .iter()
+            .filter(|(_, pending)| pending.caller_id == session_id || pending.callee_id == session_id)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+
+        let mut messages = Vec::new();
+        for request_id in request_ids {
+            let pending = self.remove_pending_call(request_id).unwrap();
+            if pending.callee_id == session_id {
+                // a session that called itself has nobody left to answer
+                if pending.caller_id != session_id {
+                    messages.push(MessageWithRecipient::new(
+                        call_error(pending.request_id, ERROR_CANCELED),
+                        pending.caller_id,
+                    ));
+                }
+
+                continue;
+            }
+
+            if !pending.interrupted && self.canceling_callees.contains(&pending.callee_id) {
+                let interrupt = Interrupt {
+                    request_id,
+                    options: CancelOptions::new().with_mode(CancelMode::KillNoWait).marshal(),
+                };
+
+                messages.push(MessageWithRecipient::new(interrupt, pending.callee_id));
+            }
+
+            // the callee may still reply, which is no reason to fail its session
+            self.orphan_call(pending.callee_id, request_id);
+        }
+
+        self.orphaned_order.retain(|(callee_id, _)| *callee_id != session_id);
+        self.orphaned_calls.retain(|(callee_id, _)| *callee_id != session_id);
+
+        messages
+    }
+
+    pub fn has_procedure(&self, procedure: &str) -> bool {
+        self.registrations_by_procedure.best_match(procedure).is_some()
+    }
+
+    pub fn registration(&self, registration_id: i64) -> Option<&Registration> {
+        self.registrations.get(&registration_id)
+    }
+
+    // time left until the next call times out, for scheduling the next expire_calls
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.call_timeouts.next_timeout()
+    }
+
+    // interrupts the callees of calls that ran past their timeout and fails them for their callers
+    pub fn expire_calls(&mut self) -> Vec<MessageWithRecipient> {
+        let mut messages = Vec::new();
+        for request_id in self.call_timeouts.expired() {
+            let pending = match self.remove_pending_call(request_id) {
+                Some(pending) => pending,
+                None => continue,
+            };
+
+            // with kill the callee was already interrupted and we were only waiting for its reply
+            if !pending.interrupted && self.canceling_callees.contains(&pending.callee_id) {
+                let mut options = CancelOptions::new().with_mode(CancelMode::KillNoWait).marshal();
+                options.insert(OPTION_REASON.to_string(), Value::str(ERROR_TIMEOUT));
+                messages.push(MessageWithRecipient::new(
+                    Interrupt { request_id, options },
+                    pending.callee_id,
+                ));
+            }
+
+            messages.push(MessageWithRecipient::new(
+                call_error(pending.request_id, ERROR_TIMEOUT),
+                pending.caller_id,
+            ));
+            self.orphan_call(pending.callee_id, request_id);
+        }
+
+        messages
+    }
+
+    pub fn receive_message(&mut self, session_id: i64, msg: WampMessage) -> Result<Vec<MessageWithRecipient>, Error> {
+        match msg {
+            WampMessage::Call(call) => Ok(vec![self.receive_call(session_id, call)?]),
+
+            WampMessage::Cancel(cancel) => self.receive_cancel(session_id, &cancel),
+
+            WampMessage::Yield(yield_) => self.receive_yield(session_id, yield_),
+
+            WampMessage::Register(register) => Ok(vec![self.receive_register(session_id, register)?]),
+
+            WampMessage::Unregister(unregister) => Ok(vec![self.receive_unregister(session_id, &unregister)]),
+
+            WampMessage::Error(error) => self.receive_error(session_id, error),
+
+            _ => Err(Error::protocol_violation(format!(
+                "dealer: received unexpected message of type {:?}",
+                msg.message_type()
+            ))),
+        }
+    }
+
+    fn receive_call(&mut self, session_id: i64, call: Call) -> Result<MessageWithRecipient, Error> {
+        if !self.registrations_by_session.contains_key(&session_id) {
+            return Err(Error::invalid_state("cannot call, session doesn't exist"));
+        }
+
+        let options = match CallOptions::parse(&call.options) {
+            Ok(options) => options,
+            Err(e) => {
+                return Ok(MessageWithRecipient::new(
+                    e.to_error_message(MESSAGE_TYPE_CALL, call.request_id),
+                    session_id,
+                ));
+            }
+        };
+
+        let progress = options.progress == Some(true);
+        if let Some(request_id) = self.pending_calls_by_caller.get(&(session_id, call.request_id)) {
+            return self.continue_call(*request_id, call, progress);
+        }
+
+        let registration = match self
+            .registrations_by_procedure
+            .best_match(&call.procedure)
+            .and_then(|id| self.registrations.get_mut(id))
+        {
+            Some(registration) => registration,
+            None => {
+                return Ok(MessageWithRecipient::new(
+                    call_error(call.request_id, ERROR_NO_SUCH_PROCEDURE),
+                    session_id,
+                ));
+            }
+        };
+
+        let callee_id = registration.next_callee(&mut self.invoke_rng);
+        let request_id = self.request_idgen.next_id();
+        let receive_progress = options.receive_progress == Some(true);
+        let mut details = HashMap::new();
+        PassthruOptions::forward(&call.options, &mut details);
+        if receive_progress {
+            details.insert(OPTION_RECEIVE_PROGRESS.to_string(), Value::Bool(true));
+        }
+
+        if progress {
+            details.insert(OPTION_PROGRESS.to_string(), Value::Bool(true));
+        }
+
+        // callees of a pattern need to know which procedure was actually called
+        if registration.match_policy != MatchPolicy::Exact {
+            details.insert(DETAIL_PROCEDURE.to_string(), Value::str(call.procedure.clone()));
+        }
+
+        let invocation = Invocation {
+            request_id,
+            registration_id: registration.id,
+            details,
+            args: call.args,
+            kwargs: call.kwargs,
+        };
+
+        self.pending_calls.insert(
+            request_id,
+            PendingInvocation {
+                request_id: call.request_id,
+                registration_id: registration.id,
+                caller_id: session_id,
+                callee_id,
+                receive_progress,
+                progressive: progress,
+                interrupted: false,
+            },
+        );
+        self.pending_calls_by_caller
+            .insert((session_id, call.request_id), request_id);
+        if let Some(timeout) = options.timeout_duration() {
+            self.call_timeouts.start(request_id, timeout);
+        }
+
+        Ok(MessageWithRecipient::new(invocation, callee_id))
+    }
+
+    // a follow-up CALL of a progressive invocation goes to the callee serving the first one
+    fn continue_call(&mut self, request_id: i64, call: Call, progress: bool) -> Result<MessageWithRecipient, Error> {
+        let pending = self.pending_calls.get_mut(&request_id).unwrap();
+        if !pending.progressive {
+            return Err(Error::protocol_violation(format!(
+                "dealer: request_id {} is already in use by a pending call",
+                call.request_id
+            )));
+        }
+
+        pending.progressive = progress;
+        let mut details = HashMap::new();
+        PassthruOptions::forward(&call.options, &mut details);
+        if progress {
+            details.insert(OPTION_PROGRESS.to_string(), Value::Bool(true));
+        }
+
+        let invocation = Invocation {
+            request_id,
+            registration_id: pending.registration_id,
+            details,
+            args: call.args,
+            kwargs: call.kwargs,
+        };
+
+        Ok(MessageWithRecipient::new(invocation, pending.callee_id))
+    }
+
+    fn receive_cancel(&mut self, session_id: i64, cancel: &Cancel) -> Result<Vec<MessageWithRecipient>, Error> {
+        let mode = CancelOptions::parse(&cancel.options)
+            .map_err(|e| Error::protocol_violation(e.message()))?
+            .mode
+            .unwrap_or_default();
+
+        // the call may have completed while the CANCEL was in flight
+        let request_id = match self.pending_calls_by_caller.get(&(session_id, cancel.request_id)) {
+            Some(request_id) => *request_id,
+            None => return Ok(Vec::new()),
+        };
+
+        let pending = self.pending_calls.get_mut(&request_id).unwrap();
+        if pending.interrupted {
+            return Ok(Vec::new());
+        }
+
+        // a callee that can't be interrupted keeps running the call, the caller is answered as with skip
+        let mode = match mode {
+            CancelMode::Kill | CancelMode::KillNoWait if !self.canceling_callees.contains(&pending.callee_id) => {
+                CancelMode::Skip
+            }
+            mode => mode,
+        };
+
+        let mut messages = Vec::new();
+        if mode != CancelMode::Skip {
+            let interrupt = Interrupt {
+                request_id,
+                options: CancelOptions::new().with_mode(mode).marshal(),
+            };
+
+            messages.push(MessageWithRecipient::new(interrupt, pending.callee_id));
+        }
+
+        if mode == CancelMode::Kill {
+            pending.interrupted = true;
+            return Ok(messages);
+        }
+
+        // the caller is answered right away, a late reply of the callee is dropped
+        let pending = self.remove_pending_call(request_id).unwrap();
+        messages.push(MessageWithRecipient::new(
+            call_error(pending.request_id, ERROR_CANCELED),
+            pending.caller_id,
+        ));
+        self.orphan_call(pending.callee_id, request_id);
+
+        Ok(messages)
+    }
+
+    fn receive_yield(&mut self, session_id: i64, yield_: Yield) -> Result<Vec<MessageWithRecipient>, Error> {
+        let options = YieldOptions::parse(&yield_.options).map_err(|e| Error::protocol_violation(e.message()))?;
+        let progress = options.progress == Some(true);
+        let pending = match self.pending_calls.get(&yield_.request_id) {
+            Some(pending) if pending.callee_id == session_id => pending,
+            _ if self.is_orphaned_call(session_id, yield_.request_id) => {
+                if !progress {
+                    self.forget_orphaned_call(session_id, yield_.request_id);
+                }
+
+                return Ok(Vec::new());
+            }
+            _ => {
+                return Err(Error::protocol_violation(format!(
+                    "dealer: no pending invocation for request_id {}",
+                    yield_.request_id
+                )));
+            }
+        };
+
+        // a caller that didn't ask for progressive results only gets the final one
+        if progress && !pending.receive_progress {
+            return Ok(Vec::new());
+        }
+
+        let (request_id, caller_id) = (pending.request_id, pending.caller_id);
+        let mut details = HashMap::new();
+        PassthruOptions::forward(&yield_.options, &mut details);
+        if progress {
+            details.insert(OPTION_PROGRESS.to_string(), Value::Bool(true));
+        } else {
+            // a final result ends the call
+            self.remove_pending_call(yield_.request_id);
+        }
+
+        let result = Result_ {
+            request_id,
+            details,
+            args: yield_.args,
+            kwargs: yield_.kwargs,
+        };
+
+        Ok(vec![MessageWithRecipient::new(result, caller_id)])
+    }
+
+    fn receive_error(&mut self, session_id: i64, error: ErrorMessage) -> Result<Vec<MessageWithRecipient>, Error> {
+        if error.message_type != MESSAGE_TYPE_INVOCATION {
+            return Err(Error::protocol_violation(
+                "dealer: only expected to receive ERROR in response to INVOCATION",
+            ));
+        }
+
+        if self.is_orphaned_call(session_id, error.request_id) {
+            self.forget_orphaned_call(session_id, error.request_id);
+            return Ok(Vec::new());
+        }
+
+        let pending = self.take_pending_call(session_id, error.request_id)?;
+
+        let error = ErrorMessage {
+            message_type: MESSAGE_TYPE_CALL,
+            request_id: pending.request_id,
+            options: error.options,
+            uri: error.uri,
+            args: error.args,
+            kwargs: error.kwargs,
+        };
+
+        Ok(vec![MessageWithRecipient::new(error, pending.caller_id)])
+    }
+
+    fn receive_register(&mut self, session_id: i64, register: Register) -> Result<MessageWithRecipient, Error> {
+        let session_registrations = match self.registrations_by_session.get_mut(&session_id) {
+            Some(registrations) => registrations,
+            None => return Err(Error::invalid_state("cannot register, session doesn't exist")),
+        };
+
+        let options = match RegisterOptions::parse(&register.options) {
+            Ok(options) => options,
+            Err(e) => {
+                return Ok(MessageWithRecipient::new(
+                    e.to_error_message(MESSAGE_TYPE_REGISTER, register.request_id),
+                    session_id,
+                ));
+            }
+        };
+
+        let match_policy = options.match_policy.unwrap_or_default();
+        let invoke_policy = options.invoke_policy.unwrap_or_default();
+        if let Some(registration_id) = self.registrations_by_procedure.get(match_policy, &register.procedure) {
+            let registration = self.registrations.get_mut(registration_id).unwrap();
+            // callees can only share a registration if all of them agree on how calls are distributed
+            if registration.invoke_policy == InvokePolicy::Single
+                || registration.invoke_policy != invoke_policy
+                || registration.registrants.contains(&session_id)
+            {
+                return Ok(MessageWithRecipient::new(
+                    request_error(
+                        MESSAGE_TYPE_REGISTER,
+                        register.request_id,
+                        ERROR_PROCEDURE_ALREADY_EXISTS,
+                    ),
+                    session_id,
+                ));
+            }
+
+            registration.registrants.push(session_id);
+            session_registrations.insert(registration.id);
+            let registered = Registered {
+                request_id: register.request_id,
+                registration_id: registration.id,
+            };
+
+            return Ok(MessageWithRecipient::new(registered, session_id));
+        }
+
+        let registration_id = self.registration_idgen.next_id();
+        session_registrations.insert(registration_id);
+        self.registrations_by_procedure
+            .insert(match_policy, &register.procedure, registration_id);
+        self.registrations.insert(
+            registration_id,
+            Registration {
+                id: registration_id,
+                procedure: register.procedure.clone(),
+                match_policy,
+                invoke_policy,
+                registrants: vec![session_id],
+                round_robin: 0,
+            },
+        );
+
+        let registered = Registered {
+            request_id: register.request_id,
+            registration_id,
+        };
+
+        Ok(MessageWithRecipient::new(registered, session_id))
+    }
+
+    fn receive_unregister(&mut self, session_id: i64, unregister: &Unregister) -> MessageWithRecipient {
+        let removed = match self.registrations_by_session.get_mut(&session_id) {
+            Some(registrations) => registrations.remove(&unregister.registration_id),
+            None => false,
+        };
+
+        if !removed {
+            return MessageWithRecipient::new(
+                request_error(
+                    MESSAGE_TYPE_UNREGISTER,
+                    unregister.request_id,
+                    ERROR_NO_SUCH_REGISTRATION,
+                ),
+                session_id,
+            );
+        }
+
+        self.remove_registrant(unregister.registration_id, session_id);
+        let unregistered = Unregistered {
+            request_id: unregister.request_id,
+        };
+
+        MessageWithRecipient::new(unregistered, session_id)
+    }
+
+    fn remove_registrant(&mut self, registration_id: i64, session_id: i64) {
+        if let Some(registration) = self.registrations.get_mut(&registration_id) {
+            registration.registrants.retain(|registrant| *registrant != session_id);
+            if registration.registrants.is_empty() {
+                self.registrations_by_procedure
+                    .remove(registration.match_policy, &registration.procedure);
+                self.registrations.remove(&registration_id);
+            }
+        }
+    }
+
+    fn remove_pending_call(&mut self, request_id: i64) -> Option<PendingInvocation> {
+        let pending = self.pending_calls.remove(&request_id)?;
+        self.call_timeouts.stop(&request_id);
+        self.pending_calls_by_caller
+            .remove(&(pending.caller_id, pending.request_id));
+
+        Some(pending)
+    }
+
+    fn orphan_call(&mut self, callee_id: i64, request_id: i64) {
+        if self.orphaned_order.len() == MAX_ORPHANED_CALLS {
+            let oldest = self.orphaned_order.pop_front().unwrap();
+            self.orphaned_calls.remove(&oldest);
+        }
+
+        self.orphaned_calls.insert((callee_id, request_id));
+        self.orphaned_order.push_back((callee_id, request_id));
+    }
+
+    fn is_orphaned_call(&self, callee_id: i64, request_id: i64) -> bool {
+        self.orphaned_calls.contains(&(callee_id, request_id))
+    }
+
+    fn forget_orphaned_call(&mut self, callee_id: i64, request_id: i64) {
+        if self.orphaned_calls.remove(&(callee_id, request_id)) {
+            self.orphaned_order.retain(|key| *key != (callee_id, request_id));
+        }
+    }
+
+    fn take_pending_call(&mut self, session_id: i64, request_id: i64) -> Result<PendingInvocation, Error> {
+        match self.pending_calls.get(&request_id) {
+            Some(pending) if pending.callee_id == session_id => Ok(self.remove_pending_call(request_id).unwrap()),
+            _ => Err(Error::protocol_violation(format!(
+                "dealer: no pending invocation for request_id {request_id}"
+            ))),
+        }
+    }
+}
+
+fn request_error(message_type: Value, request_id: i64, uri: &str) -> ErrorMessage {
+    ErrorMessage {
+        message_type,
+        request_id,
+        options: HashMap::new(),
+        uri: uri.to_string(),
+        args: None,
+        kwargs: None,
+    }
+}
+
+fn call_error(request_id: i64, uri: &str) -> ErrorMessage {
+    request_error(MESSAGE_TYPE_CALL, request_id, uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::roles::Roles;
+    use std::ops::RangeInclusive;
+
+    fn add_session(dealer: &mut Dealer, id: i64, roles: Roles) {
+        let details = SessionDetails::new(id, "realm1".to_string(), "".to_string(), "".to_string(), false);
+        dealer.add_session(&details.with_roles(roles)).unwrap();
+    }
+
+    fn dealer(sessions: &[i64]) -> Dealer {
+        let mut dealer = Dealer::new();
+        for id in sessions {
+            add_session(&mut dealer, *id, Roles::client());
+        }
+
+        dealer
+    }
+
+    fn register(dealer: &mut Dealer, session_id: i64, procedure: &str, options: RegisterOptions) -> i64 {
+        let register = Register {
+            request_id: 1,
+            options: options.marshal(),
+            procedure: procedure.to_string(),
+        };
+
+        match dealer
+            .receive_message(session_id, register.into())
+            .unwrap()
+            .remove(0)
+            .message
+        {
+            WampMessage::Registered(registered) => registered.registration_id,
+            other => panic!("expected REGISTERED, got {other:?}"),
+        }
+    }
+
+    fn call(request_id: i64, procedure: &str, options: CallOptions) -> WampMessage {
+        Call {
+            request_id,
+            options: options.marshal(),
+            procedure: procedure.to_string(),
+            args: Some(vec![Value::Int(request_id)]),
+            kwargs: None,
+        }
+        .into()
+    }
+
+    fn yield_(request_id: i64, options: YieldOptions) -> WampMessage {
+        Yield {
+            request_id,
+            options: options.marshal(),
+            args: Some(vec![Value::str("done")]),
+            kwargs: None,
+        }
+        .into()
+    }
+
+    fn invocation(request_id: i64, registration_id: i64, details: HashMap<String, Value>, arg: i64) -> Invocation {
+        Invocation {
+            request_id,
+            registration_id,
+            details,
+            args: Some(vec![Value::Int(arg)]),
+            kwargs: None,
+        }
+    }
+
+    fn result(request_id: i64, details: HashMap<String, Value>) -> Result_ {
+        Result_ {
+            request_id,
+            details,
+            args: Some(vec![Value::str("done")]),
+            kwargs: None,
+        }
+    }
+
+    fn interrupt(request_id: i64, mode: CancelMode) -> Interrupt {
+        Interrupt {
+            request_id,
+            options: CancelOptions::new().with_mode(mode).marshal(),
+        }
+    }
+
+    fn assert_messages(actual: Vec<MessageWithRecipient>, expected: Vec<MessageWithRecipient>) {
+        let marshal = |messages: Vec<MessageWithRecipient>| {
+            messages
+                .into_iter()
+                .map(|m| (m.recipient, m.message.marshal()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(marshal(actual), marshal(expected));
+    }
+
+    #[test]
+    fn call_yield() {
+        let mut dealer = dealer(&[1, 2]);
+        let registration_id = register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        assert!(dealer.has_procedure("foo.bar"));
+
+        let messages = dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(
+                invocation(1, registration_id, HashMap::new(), 5),
+                1,
+            )],
+        );
+
+        let messages = dealer.receive_message(1, yield_(1, YieldOptions::new())).unwrap();
+        assert_messages(messages, vec![MessageWithRecipient::new(result(5, HashMap::new()), 2)]);
+
+        // the call is over, a second reply is a protocol violation
+        assert!(dealer.receive_message(1, yield_(1, YieldOptions::new())).is_err());
+    }
+
+    #[test]
+    fn call_error_is_relayed() {
+        let mut dealer = dealer(&[1, 2]);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        let error = ErrorMessage {
+            message_type: MESSAGE_TYPE_INVOCATION,
+            request_id: 1,
+            options: HashMap::new(),
+            uri: "app.error".to_string(),
+            args: None,
+            kwargs: None,
+        };
+        let messages = dealer.receive_message(1, error.into()).unwrap();
+        assert_messages(messages, vec![MessageWithRecipient::new(call_error(5, "app.error"), 2)]);
+    }
+
+    #[test]
+    fn no_such_procedure() {
+        let mut dealer = dealer(&[1]);
+        let messages = dealer
+            .receive_message(1, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(call_error(5, ERROR_NO_SUCH_PROCEDURE), 1)],
+        );
+    }
+
+    #[test]
+    fn unregister() {
+        let mut dealer = dealer(&[1]);
+        let registration_id = register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+
+        let unregister = |request_id| Unregister {
+            request_id,
+            registration_id,
+        };
+        let messages = dealer.receive_message(1, unregister(2).into()).unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(Unregistered { request_id: 2 }, 1)],
+        );
+        assert!(!dealer.has_procedure("foo.bar"));
+
+        let messages = dealer.receive_message(1, unregister(3).into()).unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(
+                request_error(MESSAGE_TYPE_UNREGISTER, 3, ERROR_NO_SUCH_REGISTRATION),
+                1,
+            )],
+        );
+    }
+
+    #[test]
+    fn callee_leaving_cancels_its_calls() {
+        let mut dealer = dealer(&[1, 2]);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        let messages = dealer.remove_session(1);
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(call_error(5, ERROR_CANCELED), 2)],
+        );
+        assert!(!dealer.has_procedure("foo.bar"));
+    }
+
+    #[test]
+    fn caller_leaving_interrupts_canceling_callees() {
+        let mut dealer = dealer(&[1, 2]);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        let messages = dealer.remove_session(2);
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(interrupt(1, CancelMode::KillNoWait), 1)],
+        );
+
+        // the late reply of the callee is dropped once, after that the request_id is unknown again
+        assert!(
+            dealer
+                .receive_message(1, yield_(1, YieldOptions::new()))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(dealer.receive_message(1, yield_(1, YieldOptions::new())).is_err());
+    }
+
+    #[test]
+    fn caller_leaving_skips_other_callees() {
+        let mut dealer = dealer(&[2]);
+        add_session(&mut dealer, 1, Roles::new());
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        assert!(dealer.remove_session(2).is_empty());
+        assert!(
+            dealer
+                .receive_message(1, yield_(1, YieldOptions::new()))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn session_calling_itself_leaves() {
+        let mut dealer = dealer(&[1]);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(1, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        assert!(dealer.remove_session(1).is_empty());
+    }
+
+    fn progress(receive_progress: bool, progress: bool) -> HashMap<String, Value> {
+        let mut details = HashMap::new();
+        if receive_progress {
+            details.insert(OPTION_RECEIVE_PROGRESS.to_string(), Value::Bool(true));
+        }
+
+        if progress {
+            details.insert(OPTION_PROGRESS.to_string(), Value::Bool(true));
+        }
+
+        details
+    }
+
+    #[test]
+    fn progressive_results() {
+        let mut dealer = dealer(&[1, 2]);
+        let registration_id = register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+
+        let options = CallOptions::new().with_receive_progress(true);
+        let messages = dealer.receive_message(2, call(5, "foo.bar", options)).unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(
+                invocation(1, registration_id, progress(true, false), 5),
+                1,
+            )],
+        );
+
+        for _ in 0..2 {
+            let messages = dealer
+                .receive_message(1, yield_(1, YieldOptions::new().with_progress(true)))
+                .unwrap();
+            assert_messages(
+                messages,
+                vec![MessageWithRecipient::new(result(5, progress(false, true)), 2)],
+            );
+        }
+
+        let messages = dealer.receive_message(1, yield_(1, YieldOptions::new())).unwrap();
+        assert_messages(messages, vec![MessageWithRecipient::new(result(5, HashMap::new()), 2)]);
+        assert!(dealer.receive_message(1, yield_(1, YieldOptions::new())).is_err());
+    }
+
+    #[test]
+    fn progressive_results_dropped_unless_asked_for() {
+        let mut dealer = dealer(&[1, 2]);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        let messages = dealer
+            .receive_message(1, yield_(1, YieldOptions::new().with_progress(true)))
+            .unwrap();
+        assert!(messages.is_empty());
+
+        let messages = dealer.receive_message(1, yield_(1, YieldOptions::new())).unwrap();
+        assert_messages(messages, vec![MessageWithRecipient::new(result(5, HashMap::new()), 2)]);
+    }
+
+    #[test]
+    fn progressive_invocations() {
+        let mut dealer = dealer(&[1, 2, 3]);
+        let registration_id = register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+
+        // every progressive CALL continues the same invocation at the same callee
+        for _ in 0..2 {
+            let options = CallOptions::new().with_progress(true);
+            let messages = dealer.receive_message(2, call(5, "foo.bar", options)).unwrap();
+            assert_messages(
+                messages,
+                vec![MessageWithRecipient::new(
+                    invocation(1, registration_id, progress(false, true), 5),
+                    1,
+                )],
+            );
+        }
+
+        let messages = dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(
+                invocation(1, registration_id, HashMap::new(), 5),
+                1,
+            )],
+        );
+
+        // the caller is done sending, its request_id can't be reused until the call ends
+        assert!(
+            dealer
+                .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+                .is_err()
+        );
+
+        // another caller with the same request_id starts a call of its own
+        let messages = dealer
+            .receive_message(3, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(
+                invocation(2, registration_id, HashMap::new(), 5),
+                1,
+            )],
+        );
+
+        let messages = dealer.receive_message(1, yield_(1, YieldOptions::new())).unwrap();
+        assert_messages(messages, vec![MessageWithRecipient::new(result(5, HashMap::new()), 2)]);
+    }
+
+    fn cancel(request_id: i64, mode: CancelMode) -> WampMessage {
+        Cancel {
+            request_id,
+            options: CancelOptions::new().with_mode(mode).marshal(),
+        }
+        .into()
+    }
+
+    // a dealer with a pending call 5 of session 2, invocation 1 at session 1
+    fn pending_call(callee_roles: Roles) -> Dealer {
+        let mut dealer = dealer(&[2]);
+        add_session(&mut dealer, 1, callee_roles);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        dealer
+            .receive_message(2, call(5, "foo.bar", CallOptions::new()))
+            .unwrap();
+
+        dealer
+    }
+
+    #[test]
+    fn cancel_skip() {
+        let mut dealer = pending_call(Roles::client());
+        let messages = dealer.receive_message(2, cancel(5, CancelMode::Skip)).unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(call_error(5, ERROR_CANCELED), 2)],
+        );
+
+        // the callee never learns about it, its reply is dropped
+        assert!(
+            dealer
+                .receive_message(1, yield_(1, YieldOptions::new()))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn cancel_kill() {
+        let mut dealer = pending_call(Roles::client());
+        let messages = dealer.receive_message(2, cancel(5, CancelMode::Kill)).unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(interrupt(1, CancelMode::Kill), 1)],
+        );
+
+        // the caller waits for the callee, a repeated CANCEL changes nothing
+        assert!(
+            dealer
+                .receive_message(2, cancel(5, CancelMode::Kill))
+                .unwrap()
+                .is_empty()
+        );
+
+        let error = ErrorMessage {
+            message_type: MESSAGE_TYPE_INVOCATION,
+            request_id: 1,
+            options: HashMap::new(),
+            uri: ERROR_CANCELED.to_string(),
+            args: None,
+            kwargs: None,
+        };
+        let messages = dealer.receive_message(1, error.into()).unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(call_error(5, ERROR_CANCELED), 2)],
+        );
+    }
+
+    #[test]
+    fn cancel_killnowait() {
+        let mut dealer = pending_call(Roles::client());
+        let messages = dealer.receive_message(2, cancel(5, CancelMode::KillNoWait)).unwrap();
+        assert_messages(
+            messages,
+            vec![
+                MessageWithRecipient::new(interrupt(1, CancelMode::KillNoWait), 1),
+                MessageWithRecipient::new(call_error(5, ERROR_CANCELED), 2),
+            ],
+        );
+
+        assert!(
+            dealer
+                .receive_message(1, yield_(1, YieldOptions::new()))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            dealer
+                .receive_message(2, cancel(5, CancelMode::KillNoWait))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn cancel_without_call_canceling_skips() {
+        for mode in [CancelMode::Kill, CancelMode::KillNoWait] {
+            let mut dealer = pending_call(Roles::new());
+            let messages = dealer.receive_message(2, cancel(5, mode)).unwrap();
+            assert_messages(
+                messages,
+                vec![MessageWithRecipient::new(call_error(5, ERROR_CANCELED), 2)],
+            );
+        }
+    }
+
+    #[test]
+    fn cancel_after_result() {
+        let mut dealer = pending_call(Roles::client());
+        dealer.receive_message(1, yield_(1, YieldOptions::new())).unwrap();
+
+        assert!(
+            dealer
+                .receive_message(2, cancel(5, CancelMode::Kill))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    fn timeout_interrupt(request_id: i64) -> Interrupt {
+        let mut interrupt = interrupt(request_id, CancelMode::KillNoWait);
+        interrupt
+            .options
+            .insert(OPTION_REASON.to_string(), Value::str(ERROR_TIMEOUT));
+        interrupt
+    }
+
+    #[test]
+    fn call_timeout() {
+        let clock = Arc::new(ManualClock::new());
+        let mut dealer = dealer(&[1, 2]).with_clock(clock.clone());
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+
+        let options = CallOptions::new().with_timeout(1000);
+        dealer.receive_message(2, call(5, "foo.bar", options)).unwrap();
+        assert_eq!(dealer.next_timeout(), Some(Duration::from_secs(1)));
+
+        clock.advance(Duration::from_millis(999));
+        assert!(dealer.expire_calls().is_empty());
+
+        clock.advance(Duration::from_millis(1));
+        assert_messages(
+            dealer.expire_calls(),
+            vec![
+                MessageWithRecipient::new(timeout_interrupt(1), 1),
+                MessageWithRecipient::new(call_error(5, ERROR_TIMEOUT), 2),
+            ],
+        );
+        assert_eq!(dealer.next_timeout(), None);
+
+        // the callee's late reply is dropped
+        assert!(
+            dealer
+                .receive_message(1, yield_(1, YieldOptions::new()))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn call_timeout_without_call_canceling() {
+        let clock = Arc::new(ManualClock::new());
+        let mut dealer = pending_call(Roles::new()).with_clock(clock.clone());
+        let options = CallOptions::new().with_timeout(1000);
+        dealer.receive_message(2, call(6, "foo.bar", options)).unwrap();
+
+        clock.advance(Duration::from_secs(1));
+        assert_messages(
+            dealer.expire_calls(),
+            vec![MessageWithRecipient::new(call_error(6, ERROR_TIMEOUT), 2)],
+        );
+    }
+
+    #[test]
+    fn call_timeout_after_result() {
+        let clock = Arc::new(ManualClock::new());
+        let mut dealer = dealer(&[1, 2]).with_clock(clock.clone());
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+
+        let options = CallOptions::new().with_timeout(1000);
+        dealer.receive_message(2, call(5, "foo.bar", options)).unwrap();
+        dealer.receive_message(1, yield_(1, YieldOptions::new())).unwrap();
+
+        clock.advance(Duration::from_secs(1));
+        assert!(dealer.expire_calls().is_empty());
+    }
+
+    #[test]
+    fn match_precedence() {
+        let mut dealer = dealer(&[1, 2, 3, 4, 5, 6]);
+        let patterns = [
+            (1, "com.app.foo.bar", MatchPolicy::Exact),
+            (2, "com.app.foo", MatchPolicy::Prefix),
+            (3, "com.app", MatchPolicy::Prefix),
+            (4, "com...bar", MatchPolicy::Wildcard),
+            (5, "com.x..bar", MatchPolicy::Wildcard),
+        ];
+
+        let mut registration_ids = HashMap::new();
+        for (session_id, procedure, match_policy) in patterns {
+            let options = RegisterOptions::new().with_match_policy(match_policy);
+            registration_ids.insert(session_id, register(&mut dealer, session_id, procedure, options));
+        }
+
+        let calls = [
+            // an exact match wins over every pattern
+            ("com.app.foo.bar", 1),
+            // the longest prefix wins over shorter ones and over wildcards
+            ("com.app.foo.baz", 2),
+            ("com.app.x.bar", 3),
+            // the wildcard whose first wildcard component comes last wins
+            ("com.x.y.bar", 5),
+            ("com.y.y.bar", 4),
+        ];
+
+        for (request_id, (procedure, callee_id)) in (1..).zip(calls) {
+            let mut details = HashMap::new();
+            if callee_id != 1 {
+                details.insert(DETAIL_PROCEDURE.to_string(), Value::str(procedure));
+            }
+
+            let messages = dealer
+                .receive_message(6, call(request_id, procedure, CallOptions::new()))
+                .unwrap();
+            assert_messages(
+                messages,
+                vec![MessageWithRecipient::new(
+                    invocation(request_id, registration_ids[&callee_id], details, request_id),
+                    callee_id,
+                )],
+            );
+        }
+
+        let messages = dealer
+            .receive_message(6, call(9, "org.app.foo", CallOptions::new()))
+            .unwrap();
+        assert_messages(
+            messages,
+            vec![MessageWithRecipient::new(call_error(9, ERROR_NO_SUCH_PROCEDURE), 6)],
+        );
+    }
+
+    fn shared(invoke_policy: InvokePolicy) -> RegisterOptions {
+        RegisterOptions::new().with_invoke_policy(invoke_policy)
+    }
+
+    // the callees picked for the calls session 4 makes to the registration sessions 1 to 3 share
+    fn callees(dealer: &mut Dealer, request_ids: RangeInclusive<i64>) -> Vec<i64> {
+        request_ids
+            .map(|request_id| {
+                let call = call(request_id, "foo.bar", CallOptions::new());
+                dealer.receive_message(4, call).unwrap().remove(0).recipient
+            })
+            .collect()
+    }
+
+    fn shared_dealer(dealer: Dealer, invoke_policy: InvokePolicy) -> Dealer {
+        let mut dealer = dealer;
+        for session_id in 1..=4 {
+            add_session(&mut dealer, session_id, Roles::client());
+        }
+
+        let registration_id = register(&mut dealer, 1, "foo.bar", shared(invoke_policy));
+        for session_id in 2..=3 {
+            assert_eq!(
+                register(&mut dealer, session_id, "foo.bar", shared(invoke_policy)),
+                registration_id
+            );
+        }
+
+        dealer
+    }
+
+    #[test]
+    fn invoke_policies() {
+        let mut dealer = shared_dealer(Dealer::new(), InvokePolicy::First);
+        assert_eq!(callees(&mut dealer, 1..=3), vec![1, 1, 1]);
+
+        let mut dealer = shared_dealer(Dealer::new(), InvokePolicy::Last);
+        assert_eq!(callees(&mut dealer, 1..=3), vec![3, 3, 3]);
+
+        let mut dealer = shared_dealer(Dealer::new(), InvokePolicy::RoundRobin);
+        assert_eq!(callees(&mut dealer, 1..=4), vec![1, 2, 3, 1]);
+
+        // the remaining callees take over when one leaves
+        dealer.remove_session(2);
+        assert_eq!(callees(&mut dealer, 5..=7), vec![3, 1, 3]);
+
+        let mut dealer = shared_dealer(Dealer::new(), InvokePolicy::Random);
+        assert!(
+            callees(&mut dealer, 1..=20)
+                .iter()
+                .all(|callee| (1..=3).contains(callee))
+        );
+    }
+
+    #[test]
+    fn random_invoke_policy_is_seedable() {
+        let sequence = |seed| {
+            let mut dealer = shared_dealer(Dealer::new().with_seed(seed), InvokePolicy::Random);
+            callees(&mut dealer, 1..=32)
+        };
+
+        assert_eq!(sequence(7), sequence(7));
+        assert_ne!(sequence(7), sequence(8));
+    }
+
+    #[test]
+    fn procedure_already_exists() {
+        let mut dealer = dealer(&[1, 2]);
+        register(&mut dealer, 1, "foo.bar", RegisterOptions::new());
+        register(&mut dealer, 1, "foo.baz", shared(InvokePolicy::RoundRobin));
+
+        let conflicts = [
+            // single registrations are never shared
+            (2, "foo.bar", shared(InvokePolicy::Single)),
+            (2, "foo.bar", shared(InvokePolicy::RoundRobin)),
+            // every callee of a shared registration has to use the same policy
+            (2, "foo.baz", shared(InvokePolicy::Random)),
+            // a callee can't join a registration twice
+            (1, "foo.baz", shared(InvokePolicy::RoundRobin)),
+        ];
+
+        for (request_id, (session_id, procedure, options)) in (2..).zip(conflicts) {
+            let register = Register {
+                request_id,
+                options: options.marshal(),
+                procedure: procedure.to_string(),
+            };
+
+            let messages = dealer.receive_message(session_id, register.into()).unwrap();
+            assert_messages(
+                messages,
+                vec![MessageWithRecipient::new(
+                    request_error(MESSAGE_TYPE_REGISTER, request_id, ERROR_PROCEDURE_ALREADY_EXISTS),
+                    session_id,
+                )],
+            );
+        }
+
+        // the same URI with another match policy is a registration of its own
+        let options = RegisterOptions::new().with_match_policy(MatchPolicy::Prefix);
+        register(&mut dealer, 2, "foo.bar", options);
+    }
+}
@@ -1,4 +1,7 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, Ordering};
 
 const MAX_ID: i64 = 1 << 53;
 
@@ -29,3 +32,66 @@ impl Default for SessionScopeIDGenerator {
         Self::new()
     }
 }
+
+#[derive(Debug)]
+pub struct AtomicSessionScopeIDGenerator {
+    id: AtomicI64,
+}
+
+impl AtomicSessionScopeIDGenerator {
+    pub fn new() -> Self {
+        Self { id: AtomicI64::new(0) }
+    }
+
+    pub fn next_id(&self) -> i64 {
+        next_sequential_id(&self.id)
+    }
+}
+
+impl Default for AtomicSessionScopeIDGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// router-scope IDs are sequential just like session-scope ones, they only differ in who owns the generator
+pub type RouterScopeIDGenerator = AtomicSessionScopeIDGenerator;
+
+#[derive(Debug)]
+pub struct GlobalScopeIDGenerator {
+    rng: Mutex<StdRng>,
+}
+
+impl GlobalScopeIDGenerator {
+    pub fn new() -> Self {
+        Self {
+            rng: Mutex::new(StdRng::from_os_rng()),
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub fn next_id(&self) -> i64 {
+        self.rng.lock().unwrap().random_range(1..=MAX_ID)
+    }
+}
+
+impl Default for GlobalScopeIDGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn next_sequential_id(id: &AtomicI64) -> i64 {
+    let previous = id
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| {
+            if id == MAX_ID { Some(1) } else { Some(id + 1) }
+        })
+        .unwrap();
+
+    if previous == MAX_ID { 1 } else { previous + 1 }
+}
@@ -1,10 +1,9 @@
 use crate::authenticators::authenticator::ClientAuthenticator;
-use crate::messages::abort::{Abort, MESSAGE_TYPE_ABORT};
-use crate::messages::challenge::{Challenge, MESSAGE_TYPE_CHALLENGE};
 use crate::messages::hello::Hello;
 use crate::messages::message::Message;
 use crate::messages::types::{Error, Value};
-use crate::messages::welcome::{MESSAGE_TYPE_WELCOME, Welcome};
+use crate::messages::wamp_message::WampMessage;
+use crate::roles::Roles;
 use crate::serializers::serializer::Serializer;
 use crate::types::SessionDetails;
 use std::collections::HashMap;
@@ -17,50 +16,13 @@ const JOINER_STATE_AUTHENTICATE_SENT: JoinerState = 2;
 const JOINER_STATE_JOINED: JoinerState = 3;
 
 pub fn get_client_roles() -> HashMap<String, Value> {
-    let mut map = HashMap::new();
-
-    map.insert(
-        "caller".to_string(),
-        Value::Dict({
-            let mut inner = HashMap::new();
-            inner.insert("features".to_string(), Value::Dict(HashMap::new()));
-            inner
-        }),
-    );
-
-    map.insert(
-        "callee".to_string(),
-        Value::Dict({
-            let mut inner = HashMap::new();
-            inner.insert("features".to_string(), Value::Dict(HashMap::new()));
-            inner
-        }),
-    );
-
-    map.insert(
-        "publisher".to_string(),
-        Value::Dict({
-            let mut inner = HashMap::new();
-            inner.insert("features".to_string(), Value::Dict(HashMap::new()));
-            inner
-        }),
-    );
-
-    map.insert(
-        "subscriber".to_string(),
-        Value::Dict({
-            let mut inner = HashMap::new();
-            inner.insert("features".to_string(), Value::Dict(HashMap::new()));
-            inner
-        }),
-    );
-
-    map
+    Roles::client().marshal()
 }
 
 pub struct Joiner {
     state: JoinerState,
     realm: String,
+    roles: Roles,
     session_details: Option<SessionDetails>,
 
     serializer: Box<dyn Serializer>,
@@ -72,12 +34,18 @@ impl Joiner {
         Joiner {
             realm: realm.to_string(),
             state: JOINER_STATE_NONE,
+            roles: Roles::client(),
             session_details: None,
             serializer,
             authenticator,
         }
     }
 
+    pub fn with_roles(mut self, roles: Roles) -> Self {
+        self.roles = roles;
+        self
+    }
+
     pub fn send_hello(&mut self) -> Result<Vec<u8>, Error> {
         let method: Vec<String> = vec![self.authenticator.auth_method()];
 
@@ -85,7 +53,7 @@ impl Joiner {
             self.realm.as_str(),
             self.authenticator.authid().as_str(),
             self.authenticator.auth_extra(),
-            get_client_roles(),
+            self.roles.marshal(),
             method,
         );
 
@@ -97,7 +65,7 @@ impl Joiner {
     pub fn receive(&mut self, data: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
         match self.serializer.deserialize(data) {
             Ok(msg_in) => match self.receive_message(msg_in) {
-                Ok(Some(msg_out)) => match self.serializer.serialize(msg_out.as_ref()) {
+                Ok(Some(msg_out)) => match self.serializer.serialize_message(&msg_out) {
                     Ok(msg_out) => Ok(Some(msg_out)),
                     Err(e) => Err(e),
                 },
@@ -105,30 +73,33 @@ impl Joiner {
                 Err(e) => Err(e),
             },
 
-            Err(e) => Err(Error::new(format!("failed to deserialize message: {e:?}"))),
+            Err(e) => Err(Error::serialization(format!(
+                "failed to deserialize message: {}",
+                e.message()
+            ))),
         }
     }
 
-    pub fn receive_message(&mut self, msg: Box<dyn Message>) -> Result<Option<Box<dyn Message>>, Error> {
-        match msg.message_type() {
-            MESSAGE_TYPE_WELCOME => {
+    pub fn receive_message(&mut self, msg: WampMessage) -> Result<Option<WampMessage>, Error> {
+        match msg {
+            WampMessage::Welcome(welcome) => {
                 if self.state != JOINER_STATE_HELLO_SENT && self.state != JOINER_STATE_AUTHENTICATE_SENT {
-                    Err(Error::new("received WELCOME when it was not expected"))
+                    Err(Error::protocol_violation("received WELCOME when it was not expected"))
                 } else {
-                    let welcome = msg.as_any().downcast_ref::<Welcome>().unwrap();
-
                     let realm = if welcome.realm.is_empty() {
                         self.realm.clone()
                     } else {
-                        welcome.realm.clone()
+                        welcome.realm
                     };
-                    let details = SessionDetails::new(
-                        welcome.session_id,
-                        realm,
-                        welcome.authid.clone(),
-                        welcome.auth_role.clone(),
-                        false,
-                    );
+                    let roles = match welcome.details.get("roles") {
+                        Some(Value::Dict(roles)) => Roles::parse(roles)?,
+                        Some(v) => return Err(Error::protocol_violation(format!("Invalid type for 'roles': {v:?}"))),
+                        None => Roles::new(),
+                    };
+
+                    let details =
+                        SessionDetails::new(welcome.session_id, realm, welcome.authid, welcome.auth_role, false)
+                            .with_roles(roles);
                     self.session_details = Some(details);
 
                     self.state = JOINER_STATE_JOINED;
@@ -136,28 +107,34 @@ impl Joiner {
                 }
             }
 
-            MESSAGE_TYPE_CHALLENGE => {
+            WampMessage::Challenge(challenge) => {
                 if self.state != JOINER_STATE_HELLO_SENT {
-                    Err(Error::new("received CHALLENGE when it was not expected"))
+                    Err(Error::protocol_violation("received CHALLENGE when it was not expected"))
                 } else {
-                    let challenge = msg.as_any().downcast_ref::<Challenge>().unwrap();
-                    match self.authenticator.authenticate(challenge) {
+                    match self.authenticator.authenticate(&challenge) {
                         Ok(authenticate) => {
                             self.state = JOINER_STATE_AUTHENTICATE_SENT;
-                            Ok(Some(Box::new(authenticate)))
+                            Ok(Some(WampMessage::Authenticate(authenticate)))
                         }
 
-                        Err(e) => Err(Error::new(format!("failed to authenticate: {e:?}"))),
+                        Err(e) => Err(Error::authentication(format!(
+                            "failed to authenticate: {}",
+                            e.message()
+                        ))),
                     }
                 }
             }
 
-            MESSAGE_TYPE_ABORT => {
-                let abort = msg.as_any().downcast_ref::<Abort>().unwrap();
-                Err(Error::new(abort.reason.to_string()))
+            WampMessage::Abort(mut abort) => {
+                let message = match abort.details.remove("message") {
+                    Some(Value::Str(message)) => message,
+                    _ => "session aborted by router".to_string(),
+                };
+
+                Err(Error::application(abort.reason, message))
             }
 
-            _ => Err(Error::new(format!(
+            _ => Err(Error::protocol_violation(format!(
                 "received unknown message type {:?}",
                 msg.message_type()
             ))),
@@ -166,7 +143,7 @@ impl Joiner {
 
     pub fn session_details(&self) -> Result<Option<&SessionDetails>, Error> {
         if self.session_details.is_none() {
-            Err(Error::new("session is not setup yet"))
+            Err(Error::invalid_state("session is not setup yet"))
         } else {
             Ok(self.session_details.as_ref())
         }
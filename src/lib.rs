@@ -1,7 +1,15 @@
+pub mod acceptor;
 pub mod authenticators;
+pub mod broker;
+pub mod clock;
+pub mod dealer;
 pub mod idgen;
 pub mod joiner;
+pub mod matcher;
 pub mod messages;
+pub mod roles;
+pub mod router;
 pub mod serializers;
+pub mod session;
 pub mod transports;
 pub mod types;
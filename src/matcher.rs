@@ -0,0 +1,120 @@
+use crate::messages::options::MatchPolicy;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// maps (match policy, URI pattern) to a value, e.g. a subscription or registration ID
+#[derive(Debug)]
+pub struct UriMatcher<T> {
+    exact: HashMap<String, T>,
+    prefix: HashMap<String, T>,
+    wildcard: HashMap<String, T>,
+}
+
+impl<T> Default for UriMatcher<T> {
+    fn default() -> Self {
+        UriMatcher {
+            exact: HashMap::new(),
+            prefix: HashMap::new(),
+            wildcard: HashMap::new(),
+        }
+    }
+}
+
+impl<T> UriMatcher<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, policy: MatchPolicy, uri: &str, value: T) -> Option<T> {
+        self.patterns_mut(policy).insert(uri.to_string(), value)
+    }
+
+    pub fn get(&self, policy: MatchPolicy, uri: &str) -> Option<&T> {
+        self.patterns(policy).get(uri)
+    }
+
+    pub fn remove(&mut self, policy: MatchPolicy, uri: &str) -> Option<T> {
+        self.patterns_mut(policy).remove(uri)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.prefix.is_empty() && self.wildcard.is_empty()
+    }
+
+    // every pattern matching the URI, as used for delivering events
+    pub fn matches(&self, uri: &str) -> Vec<&T> {
+        let mut matches: Vec<&T> = self.exact.get(uri).into_iter().collect();
+        matches.extend(
+            self.prefix
+                .iter()
+                .filter(|(prefix, _)| uri.starts_with(prefix.as_str()))
+                .map(|(_, value)| value),
+        );
+        matches.extend(
+            self.wildcard
+                .iter()
+                .filter(|(pattern, _)| wildcard_matches(pattern, uri))
+                .map(|(_, value)| value),
+        );
+
+        matches
+    }
+
+    // the single most specific pattern matching the URI, as used for routing calls: an exact match wins over the
+    // longest matching prefix, which wins over the wildcard whose first wildcard component comes last
+    pub fn best_match(&self, uri: &str) -> Option<&T> {
+        if let Some(value) = self.exact.get(uri) {
+            return Some(value);
+        }
+
+        let prefix = self
+            .prefix
+            .iter()
+            .filter(|(prefix, _)| uri.starts_with(prefix.as_str()))
+            .max_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| b.cmp(a)));
+        if let Some((_, value)) = prefix {
+            return Some(value);
+        }
+
+        self.wildcard
+            .iter()
+            .filter(|(pattern, _)| wildcard_matches(pattern, uri))
+            .max_by(|(a, _), (b, _)| compare_wildcards(a, b))
+            .map(|(_, value)| value)
+    }
+
+    fn patterns(&self, policy: MatchPolicy) -> &HashMap<String, T> {
+        match policy {
+            MatchPolicy::Exact => &self.exact,
+            MatchPolicy::Prefix => &self.prefix,
+            MatchPolicy::Wildcard => &self.wildcard,
+        }
+    }
+
+    fn patterns_mut(&mut self, policy: MatchPolicy) -> &mut HashMap<String, T> {
+        match policy {
+            MatchPolicy::Exact => &mut self.exact,
+            MatchPolicy::Prefix => &mut self.prefix,
+            MatchPolicy::Wildcard => &mut self.wildcard,
+        }
+    }
+}
+
+// empty components of the pattern match any single component of the URI, e.g. "com.myapp..update"
+pub fn wildcard_matches(pattern: &str, uri: &str) -> bool {
+    let mut pattern = pattern.split('.');
+    let mut uri = uri.split('.');
+    loop {
+        match (pattern.next(), uri.next()) {
+            (None, None) => return true,
+            (Some(expected), Some(component)) if expected.is_empty() || expected == component => {}
+            _ => return false,
+        }
+    }
+}
+
+// greater means more specific: concrete components win over wildcards, earlier components decide first
+fn compare_wildcards(a: &str, b: &str) -> Ordering {
+    let concrete = |pattern: &str| -> Vec<bool> { pattern.split('.').map(|c| !c.is_empty()).collect() };
+    concrete(a).cmp(&concrete(b)).then_with(|| b.cmp(a))
+}
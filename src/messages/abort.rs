@@ -1,7 +1,13 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{
+    Message, MessageFields, ReadFields, deserialize_message, marshal_payload, parse_payload, payload_len,
+    serialize_payload,
+};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 use std::option::Option;
@@ -33,54 +39,67 @@ impl Message for Abort {
     }
 
     fn marshal(&self) -> Vec<Value> {
-        vec![
+        let mut result = vec![
             MESSAGE_TYPE_ABORT,
             Value::Dict(self.details.clone()),
             Value::Str(self.reason.clone()),
-        ]
+        ];
+
+        marshal_payload(&mut result, &self.details, &self.args, &self.kwargs);
+
+        result
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(mut data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if data.len() == VALIDATION_SPEC.min_length {
-            if let [_, Value::Dict(details), Value::Str(reason)] = &data[..] {
-                Ok(Box::new(Abort {
-                    details: details.clone(),
-                    reason: reason.clone(),
-                    args: None,
-                    kwargs: None,
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if data.len() == VALIDATION_SPEC.max_length {
-            if let [
-                _,
-                Value::Dict(details),
-                Value::Str(reason),
-                Value::List(args),
-                Value::Dict(kwargs),
-            ] = &data[..]
-            {
-                Ok(Box::new(Abort {
-                    details: details.clone(),
-                    reason: reason.clone(),
-                    args: Some(args.clone()),
-                    kwargs: Some(kwargs.clone()),
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if let [_, Value::Dict(details), Value::Str(reason), Value::List(args)] = &data[..] {
-            Ok(Box::new(Abort {
-                details: details.clone(),
-                reason: reason.clone(),
-                args: Some(args.clone()),
-                kwargs: None,
-            }))
+        let payload = data.split_off(VALIDATION_SPEC.min_length);
+        let (args, kwargs) = parse_payload(payload).ok_or_else(|| VALIDATION_SPEC.err_invalid_message())?;
+
+        if let Ok([_, Value::Dict(details), Value::Str(reason)]) = <[Value; 3]>::try_from(data) {
+            Ok(Abort {
+                details,
+                reason,
+                args,
+                kwargs,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Abort {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3 + payload_len(&self.args, &self.kwargs)))?;
+        seq.serialize_element(&MESSAGE_TYPE_ABORT)?;
+        seq.serialize_element(&self.details)?;
+        seq.serialize_element(&self.reason)?;
+        serialize_payload(&mut seq, &self.details, &self.args, &self.kwargs)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Abort {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_ABORT;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let details = fields.next()?;
+        let reason = fields.next()?;
+        let (args, kwargs) = fields.payload()?;
+
+        Ok(Abort {
+            details,
+            reason,
+            args,
+            kwargs,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Abort {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
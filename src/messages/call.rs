@@ -1,7 +1,15 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{
+    Message, MessageFields, ReadFields, deserialize_message, marshal_payload, parse_payload, payload_len,
+    serialize_payload,
+};
+use messages::options::{OPTION_PROGRESS, OPTION_RECEIVE_PROGRESS, is_set};
+use messages::passthru::{PayloadFields, PayloadFieldsMut, PayloadMessage};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 use std::option::Option;
@@ -24,6 +32,16 @@ pub struct Call {
     pub kwargs: Option<HashMap<String, Value>>,
 }
 
+impl Call {
+    pub fn progress(&self) -> bool {
+        is_set(&self.options, OPTION_PROGRESS)
+    }
+
+    pub fn receive_progress(&self) -> bool {
+        is_set(&self.options, OPTION_RECEIVE_PROGRESS)
+    }
+}
+
 impl Message for Call {
     fn as_any(&self) -> &dyn Any {
         self
@@ -41,73 +59,77 @@ impl Message for Call {
             Value::Str(self.procedure.clone()),
         ];
 
-        if let Some(args) = self.args.clone() {
-            result.push(Value::List(args));
-        }
-
-        if let Some(kwargs) = self.kwargs.clone() {
-            if self.args.is_none() {
-                result.push(Value::Null)
-            }
-
-            result.push(Value::Dict(kwargs));
-        }
+        marshal_payload(&mut result, &self.options, &self.args, &self.kwargs);
 
         result
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(mut data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if data.len() == VALIDATION_SPEC.min_length {
-            if let [_, Value::Int(request_id), Value::Dict(options), Value::Str(procedure)] = &data[..] {
-                Ok(Box::new(Call {
-                    request_id: *request_id,
-                    options: options.clone(),
-                    procedure: procedure.clone(),
-                    args: None,
-                    kwargs: None,
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if data.len() == VALIDATION_SPEC.max_length {
-            if let [
-                _,
-                Value::Int(request_id),
-                Value::Dict(options),
-                Value::Str(procedure),
-                Value::List(args),
-                Value::Dict(kwargs),
-            ] = &data[..]
-            {
-                Ok(Box::new(Call {
-                    request_id: *request_id,
-                    options: options.clone(),
-                    procedure: procedure.clone(),
-                    args: Some(args.clone()),
-                    kwargs: Some(kwargs.clone()),
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if let [
-            _,
-            Value::Int(request_id),
-            Value::Dict(options),
-            Value::Str(procedure),
-            Value::List(args),
-        ] = &data[..]
+        let payload = data.split_off(VALIDATION_SPEC.min_length);
+        let (args, kwargs) = parse_payload(payload).ok_or_else(|| VALIDATION_SPEC.err_invalid_message())?;
+
+        if let Ok([_, Value::Int(request_id), Value::Dict(options), Value::Str(procedure)]) =
+            <[Value; 4]>::try_from(data)
         {
-            Ok(Box::new(Call {
-                request_id: *request_id,
-                options: options.clone(),
-                procedure: procedure.clone(),
-                args: Some(args.clone()),
-                kwargs: None,
-            }))
+            Ok(Call {
+                request_id,
+                options,
+                procedure,
+                args,
+                kwargs,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Call {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(4 + payload_len(&self.args, &self.kwargs)))?;
+        seq.serialize_element(&MESSAGE_TYPE_CALL)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.serialize_element(&self.options)?;
+        seq.serialize_element(&self.procedure)?;
+        serialize_payload(&mut seq, &self.options, &self.args, &self.kwargs)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Call {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_CALL;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let request_id = fields.next()?;
+        let options = fields.next()?;
+        let procedure = fields.next()?;
+        let (args, kwargs) = fields.payload()?;
+
+        Ok(Call {
+            request_id,
+            options,
+            procedure,
+            args,
+            kwargs,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Call {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
+
+impl PayloadMessage for Call {
+    fn payload_fields(&self) -> PayloadFields<'_> {
+        (&self.options, &self.args, &self.kwargs)
+    }
+
+    fn payload_fields_mut(&mut self) -> PayloadFieldsMut<'_> {
+        (&mut self.options, &mut self.args, &mut self.kwargs)
+    }
+}
@@ -1,8 +1,11 @@
 use crate::messages;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
 
@@ -38,16 +41,41 @@ impl Message for Challenge {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Str(auth_method), Value::Dict(extra)] = &data[..] {
-            Ok(Box::new(Challenge {
-                auth_method: auth_method.clone(),
-                extra: extra.clone(),
-            }))
+        if let Ok([_, Value::Str(auth_method), Value::Dict(extra)]) = <[Value; 3]>::try_from(data) {
+            Ok(Challenge { auth_method, extra })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Challenge {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_CHALLENGE)?;
+        seq.serialize_element(&self.auth_method)?;
+        seq.serialize_element(&self.extra)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Challenge {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_CHALLENGE;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let auth_method = fields.next()?;
+        let extra = fields.next()?;
+
+        Ok(Challenge { auth_method, extra })
+    }
+}
+
+impl<'de> Deserialize<'de> for Challenge {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
@@ -1,7 +1,14 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{
+    Message, MessageFields, ReadFields, deserialize_message, marshal_payload, parse_payload, payload_len,
+    serialize_payload,
+};
+use messages::passthru::{PayloadFields, PayloadFieldsMut, PayloadMessage};
 use messages::types::{Error as XError, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 use std::option::Option;
@@ -43,85 +50,88 @@ impl Message for Error {
             Value::Str(self.uri.clone()),
         ];
 
-        if let Some(args) = self.args.clone() {
-            result.push(Value::List(args));
-        }
-
-        if let Some(kwargs) = self.kwargs.clone() {
-            if self.args.is_none() {
-                result.push(Value::Null)
-            }
-
-            result.push(Value::Dict(kwargs));
-        }
+        marshal_payload(&mut result, &self.options, &self.args, &self.kwargs);
 
         result
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, XError> {
+    fn parse(mut data: Vec<Value>) -> Result<Self, XError> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if data.len() == VALIDATION_SPEC.min_length {
-            if let [
-                _,
-                Value::Int(message_type),
-                Value::Int(request_id),
-                Value::Dict(options),
-                Value::Str(uri),
-            ] = &data[..]
-            {
-                Ok(Box::new(Error {
-                    message_type: Value::Int(message_type.to_owned()),
-                    request_id: *request_id,
-                    options: options.clone(),
-                    uri: uri.clone(),
-                    args: None,
-                    kwargs: None,
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if data.len() == VALIDATION_SPEC.max_length {
-            if let [
+        let payload = data.split_off(VALIDATION_SPEC.min_length);
+        let (args, kwargs) = parse_payload(payload).ok_or_else(|| VALIDATION_SPEC.err_invalid_message())?;
+
+        if let Ok(
+            [
                 _,
                 Value::Int(message_type),
                 Value::Int(request_id),
                 Value::Dict(options),
                 Value::Str(uri),
-                Value::List(args),
-                Value::Dict(kwargs),
-            ] = &data[..]
-            {
-                Ok(Box::new(Error {
-                    message_type: Value::Int(message_type.to_owned()),
-                    request_id: *request_id,
-                    options: options.clone(),
-                    uri: uri.clone(),
-                    args: Some(args.clone()),
-                    kwargs: Some(kwargs.clone()),
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if let [
-            _,
-            Value::Int(message_type),
-            Value::Int(request_id),
-            Value::Dict(options),
-            Value::Str(uri),
-            Value::List(args),
-        ] = &data[..]
+            ],
+        ) = <[Value; 5]>::try_from(data)
         {
-            Ok(Box::new(Error {
-                message_type: Value::Int(message_type.to_owned()),
-                request_id: *request_id,
-                options: options.clone(),
-                uri: uri.clone(),
-                args: Some(args.clone()),
-                kwargs: None,
-            }))
+            Ok(Error {
+                message_type: Value::Int(message_type),
+                request_id,
+                options,
+                uri,
+                args,
+                kwargs,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(5 + payload_len(&self.args, &self.kwargs)))?;
+        seq.serialize_element(&MESSAGE_TYPE_ERROR)?;
+        seq.serialize_element(&self.message_type)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.serialize_element(&self.options)?;
+        seq.serialize_element(&self.uri)?;
+        serialize_payload(&mut seq, &self.options, &self.args, &self.kwargs)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Error {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_ERROR;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let message_type = Value::Int(fields.next()?);
+        let request_id = fields.next()?;
+        let options = fields.next()?;
+        let uri = fields.next()?;
+        let (args, kwargs) = fields.payload()?;
+
+        Ok(Error {
+            message_type,
+            request_id,
+            options,
+            uri,
+            args,
+            kwargs,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
+
+impl PayloadMessage for Error {
+    fn payload_fields(&self) -> PayloadFields<'_> {
+        (&self.options, &self.args, &self.kwargs)
+    }
+
+    fn payload_fields_mut(&mut self) -> PayloadFieldsMut<'_> {
+        (&mut self.options, &mut self.args, &mut self.kwargs)
+    }
+}
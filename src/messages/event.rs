@@ -1,7 +1,15 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{
+    Message, MessageFields, ReadFields, deserialize_message, marshal_payload, parse_payload, payload_len,
+    serialize_payload,
+};
+use messages::options::{DETAIL_TOPIC, get_str};
+use messages::passthru::{PayloadFields, PayloadFieldsMut, PayloadMessage};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 use std::option::Option;
@@ -24,6 +32,13 @@ pub struct Event {
     pub kwargs: Option<HashMap<String, Value>>,
 }
 
+impl Event {
+    // the concrete URI when delivered through a pattern based subscription
+    pub fn topic(&self) -> Option<&str> {
+        get_str(&self.details, DETAIL_TOPIC)
+    }
+}
+
 impl Message for Event {
     fn as_any(&self) -> &dyn Any {
         self
@@ -41,79 +56,83 @@ impl Message for Event {
             Value::Dict(self.details.clone()),
         ];
 
-        if let Some(args) = self.args.clone() {
-            result.push(Value::List(args));
-        }
-
-        if let Some(kwargs) = self.kwargs.clone() {
-            if self.args.is_none() {
-                result.push(Value::Null)
-            }
-
-            result.push(Value::Dict(kwargs));
-        }
+        marshal_payload(&mut result, &self.details, &self.args, &self.kwargs);
 
         result
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(mut data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if data.len() == VALIDATION_SPEC.min_length {
-            if let [
-                _,
-                Value::Int(subscription_id),
-                Value::Int(publication_id),
-                Value::Dict(details),
-            ] = &data[..]
-            {
-                Ok(Box::new(Event {
-                    subscription_id: *subscription_id,
-                    publication_id: *publication_id,
-                    details: details.clone(),
-                    args: None,
-                    kwargs: None,
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if data.len() == VALIDATION_SPEC.max_length {
-            if let [
+        let payload = data.split_off(VALIDATION_SPEC.min_length);
+        let (args, kwargs) = parse_payload(payload).ok_or_else(|| VALIDATION_SPEC.err_invalid_message())?;
+
+        if let Ok(
+            [
                 _,
                 Value::Int(subscription_id),
                 Value::Int(publication_id),
                 Value::Dict(details),
-                Value::List(args),
-                Value::Dict(kwargs),
-            ] = &data[..]
-            {
-                Ok(Box::new(Event {
-                    subscription_id: *subscription_id,
-                    publication_id: *publication_id,
-                    details: details.clone(),
-                    args: Some(args.clone()),
-                    kwargs: Some(kwargs.clone()),
-                }))
-            } else {
-                Err(VALIDATION_SPEC.err_invalid_message())
-            }
-        } else if let [
-            _,
-            Value::Int(subscription_id),
-            Value::Int(publication_id),
-            Value::Dict(details),
-            Value::List(args),
-        ] = &data[..]
+            ],
+        ) = <[Value; 4]>::try_from(data)
         {
-            Ok(Box::new(Event {
-                subscription_id: *subscription_id,
-                publication_id: *publication_id,
-                details: details.clone(),
-                args: Some(args.clone()),
-                kwargs: None,
-            }))
+            Ok(Event {
+                subscription_id,
+                publication_id,
+                details,
+                args,
+                kwargs,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(4 + payload_len(&self.args, &self.kwargs)))?;
+        seq.serialize_element(&MESSAGE_TYPE_EVENT)?;
+        seq.serialize_element(&self.subscription_id)?;
+        seq.serialize_element(&self.publication_id)?;
+        seq.serialize_element(&self.details)?;
+        serialize_payload(&mut seq, &self.details, &self.args, &self.kwargs)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Event {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_EVENT;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let subscription_id = fields.next()?;
+        let publication_id = fields.next()?;
+        let details = fields.next()?;
+        let (args, kwargs) = fields.payload()?;
+
+        Ok(Event {
+            subscription_id,
+            publication_id,
+            details,
+            args,
+            kwargs,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
+
+impl PayloadMessage for Event {
+    fn payload_fields(&self) -> PayloadFields<'_> {
+        (&self.details, &self.args, &self.kwargs)
+    }
+
+    fn payload_fields_mut(&mut self) -> PayloadFieldsMut<'_> {
+        (&mut self.details, &mut self.args, &mut self.kwargs)
+    }
+}
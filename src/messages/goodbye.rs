@@ -1,8 +1,11 @@
 use crate::messages;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
 
@@ -38,16 +41,41 @@ impl Message for Goodbye {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Dict(details), Value::Str(reason)] = &data[..] {
-            Ok(Box::new(Goodbye {
-                reason: reason.clone(),
-                details: details.clone(),
-            }))
+        if let Ok([_, Value::Dict(details), Value::Str(reason)]) = <[Value; 3]>::try_from(data) {
+            Ok(Goodbye { reason, details })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Goodbye {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_GOODBYE)?;
+        seq.serialize_element(&self.details)?;
+        seq.serialize_element(&self.reason)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Goodbye {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_GOODBYE;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let details = fields.next()?;
+        let reason = fields.next()?;
+
+        Ok(Goodbye { details, reason })
+    }
+}
+
+impl<'de> Deserialize<'de> for Goodbye {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
@@ -1,7 +1,10 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -45,57 +48,59 @@ impl Message for Hello {
         vec![MESSAGE_TYPE_HELLO, Value::Str(self.realm.clone()), Value::Dict(details)]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Str(realm), Value::Dict(details)] = &data[..] {
-            let authid = match details.get("authid") {
-                Some(Value::Str(s)) => s,
-                Some(v) => return Err(Error::new(format!("Invalid type for 'authid': {v:?}"))),
-                None => return Err(Error::new("Missing field: 'authid'")),
-            };
-
-            let auth_methods = match details.get("authmethods") {
-                Some(Value::List(s)) => s.to_vec(),
-                Some(v) => {
-                    return Err(Error::new(format!("Invalid type for 'authemthods': {v:?}")));
-                }
-                None => return Err(Error::new("Missing field: 'authmethods'")),
-            };
-
-            let auth_extra = match details.get("authextra") {
-                Some(Value::Dict(s)) => s,
-                Some(v) => return Err(Error::new(format!("Invalid type for 'authextra': {v:?}"))),
-                None => return Err(Error::new("Missing field: 'authextra'")),
-            };
-
-            let roles = match details.get("roles") {
-                Some(Value::Dict(s)) => s,
-                Some(v) => return Err(Error::new(format!("Invalid type for 'authroles': {v:?}"))),
-                None => return Err(Error::new("Missing field: 'authroles'")),
-            };
-
-            let mut methods: Vec<String> = Default::default();
-            for method in auth_methods {
-                // essentially ignore any invalid authmethod
-                if let Value::Str(str) = method {
-                    methods.push(str.to_string());
-                }
-            }
-
-            Ok(Box::new(Hello::new(
-                realm,
-                authid,
-                auth_extra.clone(),
-                roles.clone(),
-                methods,
-            )))
+        if let Ok([_, Value::Str(realm), Value::Dict(details)]) = <[Value; 3]>::try_from(data) {
+            Hello::from_details(realm, details)
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
 
+impl Serialize for Hello {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_HELLO)?;
+        seq.serialize_element(&self.realm)?;
+        seq.serialize_element(&HelloDetails(self))?;
+        seq.end()
+    }
+}
+
+// the details dict of a HELLO, written from the fields without building it first
+struct HelloDetails<'a>(&'a Hello);
+
+impl Serialize for HelloDetails<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("authid", &self.0.authid)?;
+        map.serialize_entry("authmethods", &self.0.auth_methods)?;
+        map.serialize_entry("authextra", &self.0.auth_extra)?;
+        map.serialize_entry("roles", &self.0.roles)?;
+        map.end()
+    }
+}
+
+impl ReadFields for Hello {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_HELLO;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let realm = fields.next()?;
+        let details = fields.next()?;
+
+        Hello::from_details(realm, details).map_err(|e| fields.invalid(e))
+    }
+}
+
+impl<'de> Deserialize<'de> for Hello {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
+
 impl Hello {
     pub fn new(
         realm: &str,
@@ -112,4 +117,52 @@ impl Hello {
             roles,
         }
     }
+
+    fn from_details(realm: String, mut details: HashMap<String, Value>) -> Result<Hello, Error> {
+        let authid = match details.remove("authid") {
+            Some(Value::Str(s)) => s,
+            Some(v) => return Err(Error::protocol_violation(format!("Invalid type for 'authid': {v:?}"))),
+            None => return Err(Error::protocol_violation("Missing field: 'authid'")),
+        };
+
+        let auth_methods = match details.remove("authmethods") {
+            Some(Value::List(s)) => s,
+            Some(v) => {
+                return Err(Error::protocol_violation(format!(
+                    "Invalid type for 'authemthods': {v:?}"
+                )));
+            }
+            None => return Err(Error::protocol_violation("Missing field: 'authmethods'")),
+        };
+
+        let auth_extra = match details.remove("authextra") {
+            Some(Value::Dict(s)) => s,
+            Some(v) => {
+                return Err(Error::protocol_violation(format!(
+                    "Invalid type for 'authextra': {v:?}"
+                )));
+            }
+            None => return Err(Error::protocol_violation("Missing field: 'authextra'")),
+        };
+
+        let roles = match details.remove("roles") {
+            Some(Value::Dict(s)) => s,
+            Some(v) => {
+                return Err(Error::protocol_violation(format!(
+                    "Invalid type for 'authroles': {v:?}"
+                )));
+            }
+            None => return Err(Error::protocol_violation("Missing field: 'authroles'")),
+        };
+
+        let mut methods: Vec<String> = Default::default();
+        for method in auth_methods {
+            // essentially ignore any invalid authmethod
+            if let Value::Str(str) = method {
+                methods.push(str);
+            }
+        }
+
+        Ok(Hello::new(&realm, &authid, auth_extra, roles, methods))
+    }
 }
@@ -1,7 +1,10 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 
@@ -37,16 +40,41 @@ impl Message for Interrupt {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Int(request_id), Value::Dict(options)] = &data[..] {
-            Ok(Box::new(Interrupt {
-                request_id: *request_id,
-                options: options.clone(),
-            }))
+        if let Ok([_, Value::Int(request_id), Value::Dict(options)]) = <[Value; 3]>::try_from(data) {
+            Ok(Interrupt { request_id, options })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Interrupt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_INTERRUPT)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.serialize_element(&self.options)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Interrupt {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_INTERRUPT;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let request_id = fields.next()?;
+        let options = fields.next()?;
+
+        Ok(Interrupt { request_id, options })
+    }
+}
+
+impl<'de> Deserialize<'de> for Interrupt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
@@ -1,13 +1,440 @@
 use crate::messages;
 
+use messages::passthru::OPTION_PPT_SCHEME;
 use messages::types::{Error, Value};
+use messages::validator::ValidationSpec;
+use serde::de::{Error as _, IgnoredAny, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer};
 use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 
 pub trait Message: Send {
     fn as_any(&self) -> &dyn Any;
     fn message_type(&self) -> Value;
     fn marshal(&self) -> Vec<Value>;
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error>
+    fn parse(data: Vec<Value>) -> Result<Self, Error>
     where
         Self: Sized;
 }
+
+// a passthru payload travels as a bare binary in place of the args list, with no kwargs following it
+fn passthru_binary<'a>(
+    options: &HashMap<String, Value>,
+    args: &'a Option<Vec<Value>>,
+    kwargs: &Option<HashMap<String, Value>>,
+) -> Option<&'a Value> {
+    match (args.as_deref(), kwargs) {
+        (Some([payload @ Value::Bytes(_)]), None) if options.contains_key(OPTION_PPT_SCHEME) => Some(payload),
+        _ => None,
+    }
+}
+
+// args/kwargs trail every payload carrying message, args is sent as an empty list when only kwargs are present
+pub(crate) fn marshal_payload(
+    result: &mut Vec<Value>,
+    options: &HashMap<String, Value>,
+    args: &Option<Vec<Value>>,
+    kwargs: &Option<HashMap<String, Value>>,
+) {
+    if let Some(payload) = passthru_binary(options, args, kwargs) {
+        result.push(payload.clone());
+        return;
+    }
+
+    match (args, kwargs) {
+        (Some(args), _) => result.push(Value::List(args.clone())),
+        (None, Some(_)) => result.push(Value::List(vec![])),
+        (None, None) => {}
+    }
+
+    if let Some(kwargs) = kwargs {
+        result.push(Value::Dict(kwargs.clone()));
+    }
+}
+
+// inverse of marshal_payload, takes the elements following the fixed fields of a message. args in front of
+// kwargs read back as absent when empty, or null as some peers send them. A bare binary is a passthru payload
+// and reads back as the only argument
+pub(crate) type ParsedPayload = (Option<Vec<Value>>, Option<HashMap<String, Value>>);
+
+pub(crate) fn parse_payload(payload: Vec<Value>) -> Option<ParsedPayload> {
+    let mut payload = payload.into_iter();
+    let parsed = match (payload.next(), payload.next()) {
+        (None, _) => (None, None),
+        (Some(Value::Bytes(payload)), None) => (Some(vec![Value::Bytes(payload)]), None),
+        (Some(Value::List(args)), None) => (Some(args), None),
+        (Some(Value::List(args)), Some(Value::Dict(kwargs))) if args.is_empty() => (None, Some(kwargs)),
+        (Some(Value::List(args)), Some(Value::Dict(kwargs))) => (Some(args), Some(kwargs)),
+        (Some(Value::Null), Some(Value::Dict(kwargs))) => (None, Some(kwargs)),
+        _ => return None,
+    };
+
+    match payload.next() {
+        Some(_) => None,
+        None => Some(parsed),
+    }
+}
+
+// number of trailing elements serialize_payload writes, mirroring marshal
+pub(crate) fn payload_len(args: &Option<Vec<Value>>, kwargs: &Option<HashMap<String, Value>>) -> usize {
+    match (args, kwargs) {
+        (_, Some(_)) => 2,
+        (Some(_), None) => 1,
+        (None, None) => 0,
+    }
+}
+
+pub(crate) fn serialize_payload<S: SerializeSeq>(
+    seq: &mut S,
+    options: &HashMap<String, Value>,
+    args: &Option<Vec<Value>>,
+    kwargs: &Option<HashMap<String, Value>>,
+) -> Result<(), S::Error> {
+    if let Some(payload) = passthru_binary(options, args, kwargs) {
+        return seq.serialize_element(payload);
+    }
+
+    match (args, kwargs) {
+        (Some(args), _) => seq.serialize_element(args)?,
+        (None, Some(_)) => seq.serialize_element(&[] as &[Value])?,
+        (None, None) => {}
+    }
+
+    if let Some(kwargs) = kwargs {
+        seq.serialize_element(kwargs)?;
+    }
+
+    Ok(())
+}
+
+// typed counterpart of Message::parse, reads the fields following the message type straight off the wire
+pub(crate) trait ReadFields: Sized {
+    const MESSAGE_TYPE: Value;
+    const SPEC: &'static ValidationSpec;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error>;
+}
+
+pub(crate) struct MessageFields<A> {
+    seq: A,
+    spec: &'static ValidationSpec,
+    len: usize,
+}
+
+impl<'de, A: SeqAccess<'de>> MessageFields<A> {
+    fn next_optional<T: Deserialize<'de>>(&mut self) -> Result<Option<T>, A::Error> {
+        let element = self
+            .seq
+            .next_element()
+            .map_err(|e| A::Error::custom(format!("{}: {e}", self.spec.err_invalid_message().message())))?;
+
+        if element.is_some() {
+            self.len += 1;
+        }
+
+        Ok(element)
+    }
+
+    pub(crate) fn next<T: Deserialize<'de>>(&mut self) -> Result<T, A::Error> {
+        match self.next_optional()? {
+            Some(element) => Ok(element),
+            None => Err(A::Error::custom(self.spec.err_too_short(self.len).message())),
+        }
+    }
+
+    pub(crate) fn invalid(&self, err: Error) -> A::Error {
+        A::Error::custom(err.message())
+    }
+
+    // same rules as parse_payload
+    pub(crate) fn payload(&mut self) -> Result<ParsedPayload, A::Error> {
+        let args = match self.next_optional()? {
+            None => return Ok((None, None)),
+            Some(Value::Bytes(payload)) => return Ok((Some(vec![Value::Bytes(payload)]), None)),
+            Some(Value::List(args)) => Some(args),
+            Some(Value::Null) => None,
+            Some(_) => return Err(self.invalid(self.spec.err_invalid_message())),
+        };
+
+        match (args, self.next_optional()?) {
+            (None, None) => Err(self.invalid(self.spec.err_invalid_message())),
+            (Some(args), Some(kwargs)) if args.is_empty() => Ok((None, Some(kwargs))),
+            parsed => Ok(parsed),
+        }
+    }
+
+    fn end(mut self) -> Result<(), A::Error> {
+        let len = self.len;
+        while self.seq.next_element::<IgnoredAny>()?.is_some() {
+            self.len += 1;
+        }
+
+        match self.len == len {
+            true => Ok(()),
+            false => Err(self.invalid(self.spec.err_too_long(self.len))),
+        }
+    }
+}
+
+// reads the rest of a message array whose type element has already been consumed
+pub(crate) fn read_message<'de, A, M>(seq: A) -> Result<M, A::Error>
+where
+    A: SeqAccess<'de>,
+    M: ReadFields,
+{
+    let mut fields = MessageFields {
+        seq,
+        spec: M::SPEC,
+        len: 1,
+    };
+
+    let message = M::read_fields(&mut fields)?;
+    fields.end()?;
+
+    Ok(message)
+}
+
+struct MessageVisitor<M>(PhantomData<M>);
+
+impl<'de, M: ReadFields> Visitor<'de> for MessageVisitor<M> {
+    type Value = M;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a {} message array", M::SPEC.name)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<M, A::Error> {
+        match seq.next_element::<Value>()? {
+            Some(message_type) if message_type == M::MESSAGE_TYPE => read_message(seq),
+            message_type => Err(A::Error::custom(format!(
+                "expected message of type {:?}, got {message_type:?}",
+                M::MESSAGE_TYPE
+            ))),
+        }
+    }
+}
+
+pub(crate) fn deserialize_message<'de, D, M>(deserializer: D) -> Result<M, D::Error>
+where
+    D: Deserializer<'de>,
+    M: ReadFields,
+{
+    deserializer.deserialize_seq(MessageVisitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::abort::Abort;
+    use crate::messages::call::Call;
+    use crate::messages::error::Error as ErrorMessage;
+    use crate::messages::event::{Event, MESSAGE_TYPE_EVENT};
+    use crate::messages::invocation::Invocation;
+    use crate::messages::passthru::{PassthruOptions, Payload, PayloadMessage};
+    use crate::messages::publish::Publish;
+    use crate::messages::result::Result_;
+    use crate::messages::types::ByteArray;
+    use crate::messages::wamp_message::WampMessage;
+    use crate::messages::yield_::Yield;
+    use crate::serializers::cbor::CBORSerializer;
+    use crate::serializers::json::JSONSerializer;
+    use crate::serializers::msgpack::MsgPackSerializer;
+    use crate::serializers::serializer::Serializer;
+
+    fn payloads() -> Vec<ParsedPayload> {
+        let args = vec![Value::Int(1), Value::str("two"), Value::Bytes(ByteArray(vec![0, 3]))];
+        let kwargs = HashMap::from([("key".to_string(), Value::list(vec![Value::Bool(true), Value::Null]))]);
+
+        vec![
+            (None, None),
+            (Some(args.clone()), None),
+            (None, Some(kwargs.clone())),
+            (Some(args), Some(kwargs)),
+        ]
+    }
+
+    fn serializers() -> Vec<Box<dyn Serializer>> {
+        vec![
+            Box::new(JSONSerializer {}),
+            Box::new(MsgPackSerializer {}),
+            Box::new(CBORSerializer {}),
+        ]
+    }
+
+    macro_rules! payload_round_trip {
+        ($test:ident, $message:ident, $variant:ident, $build:expr) => {
+            #[test]
+            fn $test() {
+                let build = $build;
+                for (args, kwargs) in payloads() {
+                    let expected = (args.clone(), kwargs.clone());
+                    let message: $message = build(args.clone(), kwargs.clone());
+
+                    let marshaled = message.marshal();
+                    if args.is_none() && kwargs.is_some() {
+                        assert_eq!(marshaled[marshaled.len() - 2], Value::List(vec![]));
+                    }
+
+                    let parsed = $message::parse(marshaled).unwrap();
+                    assert_eq!((parsed.args, parsed.kwargs), expected);
+
+                    for serializer in serializers() {
+                        let serialized = serializer.serialize(&message).unwrap();
+                        let wamp_message = WampMessage::from(build(args.clone(), kwargs.clone()));
+                        assert_eq!(serializer.serialize_message(&wamp_message).unwrap(), serialized);
+
+                        match serializer.deserialize(serialized).unwrap() {
+                            WampMessage::$variant(deserialized) => {
+                                assert_eq!((deserialized.args, deserialized.kwargs), expected)
+                            }
+                            other => panic!("expected {}, got {other:?}", stringify!($variant)),
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    payload_round_trip!(abort_payload, Abort, Abort, |args, kwargs| Abort {
+        details: HashMap::new(),
+        reason: "wamp.close.system_shutdown".to_string(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(call_payload, Call, Call, |args, kwargs| Call {
+        request_id: 1,
+        options: HashMap::new(),
+        procedure: "io.xconn.echo".to_string(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(error_payload, ErrorMessage, Error, |args, kwargs| ErrorMessage {
+        message_type: Value::Int(48),
+        request_id: 1,
+        options: HashMap::new(),
+        uri: "wamp.error.runtime_error".to_string(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(event_payload, Event, Event, |args, kwargs| Event {
+        subscription_id: 1,
+        publication_id: 2,
+        details: HashMap::new(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(invocation_payload, Invocation, Invocation, |args, kwargs| Invocation {
+        request_id: 1,
+        registration_id: 2,
+        details: HashMap::new(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(publish_payload, Publish, Publish, |args, kwargs| Publish {
+        request_id: 1,
+        options: HashMap::new(),
+        topic: "io.xconn.topic".to_string(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(result_payload, Result_, Result, |args, kwargs| Result_ {
+        request_id: 1,
+        details: HashMap::new(),
+        args,
+        kwargs,
+    });
+
+    payload_round_trip!(yield_payload, Yield, Yield, |args, kwargs| Yield {
+        request_id: 1,
+        options: HashMap::new(),
+        args,
+        kwargs,
+    });
+
+    #[test]
+    fn null_args_accepted() {
+        let kwargs = HashMap::from([("key".to_string(), Value::Int(1))]);
+        let data = vec![
+            MESSAGE_TYPE_EVENT,
+            Value::Int(1),
+            Value::Int(2),
+            Value::Dict(HashMap::new()),
+            Value::Null,
+            Value::Dict(kwargs.clone()),
+        ];
+
+        let parsed = Event::parse(data).unwrap();
+        assert_eq!((parsed.args, parsed.kwargs), (None, Some(kwargs.clone())));
+
+        let serializer = JSONSerializer {};
+        match serializer
+            .deserialize(br#"[36, 1, 2, {}, null, {"key": 1}]"#.to_vec())
+            .unwrap()
+        {
+            WampMessage::Event(event) => assert_eq!((event.args, event.kwargs), (None, Some(kwargs))),
+            other => panic!("expected Event, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn passthru_wire_format() {
+        let options = PassthruOptions::new("x");
+        let mut publish = Publish {
+            request_id: 1,
+            options: HashMap::new(),
+            topic: "p".to_string(),
+            args: None,
+            kwargs: None,
+        };
+        publish.set_payload(Payload::Passthru {
+            options: options.clone(),
+            payload: vec![1, 2, 3],
+        });
+
+        // [16, 1, {"ppt_scheme": "x"}, "p", <binary 010203>] as each serializer puts it on the wire
+        let mut cbor = vec![0x85, 0x10, 0x01, 0xa1, 0x6a];
+        cbor.extend_from_slice(b"ppt_scheme");
+        cbor.extend_from_slice(&[0x61, b'x', 0x61, b'p', 0x43, 1, 2, 3]);
+        let mut msgpack = vec![0x95, 0x10, 0x01, 0x81, 0xaa];
+        msgpack.extend_from_slice(b"ppt_scheme");
+        msgpack.extend_from_slice(&[0xa1, b'x', 0xa1, b'p', 0xc4, 0x03, 1, 2, 3]);
+        let wire: Vec<(Box<dyn Serializer>, Vec<u8>)> = vec![
+            (
+                Box::new(JSONSerializer {}),
+                br#"[16,1,{"ppt_scheme":"x"},"p","\u0000AQID"]"#.to_vec(),
+            ),
+            (Box::new(MsgPackSerializer {}), msgpack),
+            (Box::new(CBORSerializer {}), cbor),
+        ];
+
+        for (serializer, data) in wire {
+            assert_eq!(serializer.serialize(&publish).unwrap(), data);
+            let message = WampMessage::Publish(Publish::parse(publish.marshal()).unwrap());
+            assert_eq!(serializer.serialize_message(&message).unwrap(), data);
+
+            match serializer.deserialize(data).unwrap() {
+                WampMessage::Publish(publish) => assert_eq!(
+                    publish.payload().unwrap(),
+                    Payload::Passthru {
+                        options: options.clone(),
+                        payload: vec![1, 2, 3],
+                    }
+                ),
+                other => panic!("expected Publish, got {other:?}"),
+            }
+        }
+
+        // nothing may follow the binary
+        let data = br#"[16,1,{"ppt_scheme":"x"},"p","\u0000AQID",{}]"#.to_vec();
+        assert!(JSONSerializer {}.deserialize(data).is_err());
+    }
+}
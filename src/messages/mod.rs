@@ -10,6 +10,8 @@ pub mod hello;
 pub mod interrupt;
 pub mod invocation;
 pub mod message;
+pub mod options;
+pub mod passthru;
 pub mod publish;
 pub mod published;
 pub mod register;
@@ -23,5 +25,6 @@ pub mod unregistered;
 pub mod unsubscribe;
 pub mod unsubscribed;
 pub mod validator;
+pub mod wamp_message;
 pub mod welcome;
 pub mod yield_;
@@ -0,0 +1,590 @@
+use crate::messages::types::{Error, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const OPTION_TIMEOUT: &str = "timeout";
+pub const OPTION_RECEIVE_PROGRESS: &str = "receive_progress";
+pub const OPTION_PROGRESS: &str = "progress";
+pub const OPTION_DISCLOSE_ME: &str = "disclose_me";
+pub const OPTION_DISCLOSE_CALLER: &str = "disclose_caller";
+pub const OPTION_MATCH: &str = "match";
+pub const OPTION_INVOKE: &str = "invoke";
+pub const OPTION_ACKNOWLEDGE: &str = "acknowledge";
+pub const OPTION_EXCLUDE_ME: &str = "exclude_me";
+pub const OPTION_EXCLUDE: &str = "exclude";
+pub const OPTION_EXCLUDE_AUTHID: &str = "exclude_authid";
+pub const OPTION_EXCLUDE_AUTHROLE: &str = "exclude_authrole";
+pub const OPTION_ELIGIBLE: &str = "eligible";
+pub const OPTION_ELIGIBLE_AUTHID: &str = "eligible_authid";
+pub const OPTION_ELIGIBLE_AUTHROLE: &str = "eligible_authrole";
+pub const OPTION_RETAIN: &str = "retain";
+pub const OPTION_MODE: &str = "mode";
+pub const OPTION_REASON: &str = "reason";
+
+// set by the router on EVENT/INVOCATION for pattern based subscriptions/registrations
+pub const DETAIL_TOPIC: &str = "topic";
+pub const DETAIL_PROCEDURE: &str = "procedure";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MatchPolicy {
+    #[default]
+    Exact,
+    Prefix,
+    Wildcard,
+}
+
+impl MatchPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchPolicy::Exact => "exact",
+            MatchPolicy::Prefix => "prefix",
+            MatchPolicy::Wildcard => "wildcard",
+        }
+    }
+}
+
+impl FromStr for MatchPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Error> {
+        match value {
+            "exact" => Ok(MatchPolicy::Exact),
+            "prefix" => Ok(MatchPolicy::Prefix),
+            "wildcard" => Ok(MatchPolicy::Wildcard),
+            _ => Err(Error::invalid_argument(format!("Invalid match policy '{value}'"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum InvokePolicy {
+    #[default]
+    Single,
+    First,
+    Last,
+    RoundRobin,
+    Random,
+}
+
+impl InvokePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvokePolicy::Single => "single",
+            InvokePolicy::First => "first",
+            InvokePolicy::Last => "last",
+            InvokePolicy::RoundRobin => "roundrobin",
+            InvokePolicy::Random => "random",
+        }
+    }
+}
+
+impl FromStr for InvokePolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Error> {
+        match value {
+            "single" => Ok(InvokePolicy::Single),
+            "first" => Ok(InvokePolicy::First),
+            "last" => Ok(InvokePolicy::Last),
+            "roundrobin" => Ok(InvokePolicy::RoundRobin),
+            "random" => Ok(InvokePolicy::Random),
+            _ => Err(Error::invalid_argument(format!("Invalid invoke policy '{value}'"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CancelMode {
+    // the caller gets an ERROR right away, the callee isn't told and its result is discarded
+    Skip,
+    // the callee is interrupted and the caller gets the callee's reply
+    Kill,
+    // the callee is interrupted and the caller gets an ERROR right away
+    #[default]
+    KillNoWait,
+}
+
+impl CancelMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelMode::Skip => "skip",
+            CancelMode::Kill => "kill",
+            CancelMode::KillNoWait => "killnowait",
+        }
+    }
+}
+
+impl FromStr for CancelMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Error> {
+        match value {
+            "skip" => Ok(CancelMode::Skip),
+            "kill" => Ok(CancelMode::Kill),
+            "killnowait" => Ok(CancelMode::KillNoWait),
+            _ => Err(Error::invalid_argument(format!("Invalid cancel mode '{value}'"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallOptions {
+    // milliseconds, 0 means no timeout
+    pub timeout: Option<i64>,
+    pub receive_progress: Option<bool>,
+    // set on every CALL of a progressive invocation except the last one
+    pub progress: Option<bool>,
+    pub disclose_me: Option<bool>,
+}
+
+impl CallOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: i64) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_receive_progress(mut self, receive_progress: bool) -> Self {
+        self.receive_progress = Some(receive_progress);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_disclose_me(mut self, disclose_me: bool) -> Self {
+        self.disclose_me = Some(disclose_me);
+        self
+    }
+
+    // None when the call has no timeout
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        match self.timeout {
+            Some(timeout) if timeout > 0 => Some(Duration::from_millis(timeout as u64)),
+            _ => None,
+        }
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        self.marshal_into(&mut options);
+        options
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        set_option(options, OPTION_TIMEOUT, self.timeout.map(Value::Int));
+        set_option(options, OPTION_RECEIVE_PROGRESS, self.receive_progress.map(Value::Bool));
+        set_option(options, OPTION_PROGRESS, self.progress.map(Value::Bool));
+        set_option(options, OPTION_DISCLOSE_ME, self.disclose_me.map(Value::Bool));
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Self, Error> {
+        let timeout = optional_int(options, OPTION_TIMEOUT)?;
+        if let Some(timeout) = timeout
+            && timeout < 0
+        {
+            return Err(Error::invalid_argument(format!(
+                "'{OPTION_TIMEOUT}' must not be negative, got {timeout}"
+            )));
+        }
+
+        Ok(CallOptions {
+            timeout,
+            receive_progress: optional_bool(options, OPTION_RECEIVE_PROGRESS)?,
+            progress: optional_bool(options, OPTION_PROGRESS)?,
+            disclose_me: optional_bool(options, OPTION_DISCLOSE_ME)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct YieldOptions {
+    // set on every YIELD of a progressive result except the last one
+    pub progress: Option<bool>,
+}
+
+impl YieldOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        self.marshal_into(&mut options);
+        options
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        set_option(options, OPTION_PROGRESS, self.progress.map(Value::Bool));
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Self, Error> {
+        Ok(YieldOptions {
+            progress: optional_bool(options, OPTION_PROGRESS)?,
+        })
+    }
+}
+
+// options of CANCEL, the dealer sends the same options along with INTERRUPT
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CancelOptions {
+    pub mode: Option<CancelMode>,
+}
+
+impl CancelOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mode(mut self, mode: CancelMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        self.marshal_into(&mut options);
+        options
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        set_option(options, OPTION_MODE, self.mode.map(|m| Value::str(m.as_str())));
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Self, Error> {
+        Ok(CancelOptions {
+            mode: optional_parsed(options, OPTION_MODE)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegisterOptions {
+    pub match_policy: Option<MatchPolicy>,
+    pub invoke_policy: Option<InvokePolicy>,
+    pub disclose_caller: Option<bool>,
+}
+
+impl RegisterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_match_policy(mut self, match_policy: MatchPolicy) -> Self {
+        self.match_policy = Some(match_policy);
+        self
+    }
+
+    pub fn with_invoke_policy(mut self, invoke_policy: InvokePolicy) -> Self {
+        self.invoke_policy = Some(invoke_policy);
+        self
+    }
+
+    pub fn with_disclose_caller(mut self, disclose_caller: bool) -> Self {
+        self.disclose_caller = Some(disclose_caller);
+        self
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        self.marshal_into(&mut options);
+        options
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        set_option(options, OPTION_MATCH, self.match_policy.map(|p| Value::str(p.as_str())));
+        set_option(
+            options,
+            OPTION_INVOKE,
+            self.invoke_policy.map(|p| Value::str(p.as_str())),
+        );
+        set_option(options, OPTION_DISCLOSE_CALLER, self.disclose_caller.map(Value::Bool));
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Self, Error> {
+        Ok(RegisterOptions {
+            match_policy: optional_parsed(options, OPTION_MATCH)?,
+            invoke_policy: optional_parsed(options, OPTION_INVOKE)?,
+            disclose_caller: optional_bool(options, OPTION_DISCLOSE_CALLER)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscribeOptions {
+    pub match_policy: Option<MatchPolicy>,
+}
+
+impl SubscribeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_match_policy(mut self, match_policy: MatchPolicy) -> Self {
+        self.match_policy = Some(match_policy);
+        self
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        self.marshal_into(&mut options);
+        options
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        set_option(options, OPTION_MATCH, self.match_policy.map(|p| Value::str(p.as_str())));
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Self, Error> {
+        Ok(SubscribeOptions {
+            match_policy: optional_parsed(options, OPTION_MATCH)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PublishOptions {
+    pub acknowledge: Option<bool>,
+    pub exclude_me: Option<bool>,
+    pub exclude: Option<Vec<i64>>,
+    pub exclude_authid: Option<Vec<String>>,
+    pub exclude_authrole: Option<Vec<String>>,
+    pub eligible: Option<Vec<i64>>,
+    pub eligible_authid: Option<Vec<String>>,
+    pub eligible_authrole: Option<Vec<String>>,
+    pub retain: Option<bool>,
+    pub disclose_me: Option<bool>,
+}
+
+impl PublishOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_acknowledge(mut self, acknowledge: bool) -> Self {
+        self.acknowledge = Some(acknowledge);
+        self
+    }
+
+    pub fn with_exclude_me(mut self, exclude_me: bool) -> Self {
+        self.exclude_me = Some(exclude_me);
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: Vec<i64>) -> Self {
+        self.exclude = Some(exclude);
+        self
+    }
+
+    pub fn with_exclude_authid(mut self, exclude_authid: Vec<String>) -> Self {
+        self.exclude_authid = Some(exclude_authid);
+        self
+    }
+
+    pub fn with_exclude_authrole(mut self, exclude_authrole: Vec<String>) -> Self {
+        self.exclude_authrole = Some(exclude_authrole);
+        self
+    }
+
+    pub fn with_eligible(mut self, eligible: Vec<i64>) -> Self {
+        self.eligible = Some(eligible);
+        self
+    }
+
+    pub fn with_eligible_authid(mut self, eligible_authid: Vec<String>) -> Self {
+        self.eligible_authid = Some(eligible_authid);
+        self
+    }
+
+    pub fn with_eligible_authrole(mut self, eligible_authrole: Vec<String>) -> Self {
+        self.eligible_authrole = Some(eligible_authrole);
+        self
+    }
+
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = Some(retain);
+        self
+    }
+
+    pub fn with_disclose_me(mut self, disclose_me: bool) -> Self {
+        self.disclose_me = Some(disclose_me);
+        self
+    }
+
+    // the router excludes the publisher unless it explicitly asks otherwise
+    pub fn excludes_publisher(&self) -> bool {
+        self.exclude_me.unwrap_or(true)
+    }
+
+    // whether the black- and whitelists of the publication let an event through to the subscriber
+    pub fn admits(&self, session_id: i64, authid: &str, auth_role: &str) -> bool {
+        let excluded = self.exclude.as_ref().is_some_and(|ids| ids.contains(&session_id))
+            || self
+                .exclude_authid
+                .as_ref()
+                .is_some_and(|authids| authids.iter().any(|id| id == authid))
+            || self
+                .exclude_authrole
+                .as_ref()
+                .is_some_and(|roles| roles.iter().any(|role| role == auth_role));
+
+        let eligible = self.eligible.as_ref().is_none_or(|ids| ids.contains(&session_id))
+            && self
+                .eligible_authid
+                .as_ref()
+                .is_none_or(|authids| authids.iter().any(|id| id == authid))
+            && self
+                .eligible_authrole
+                .as_ref()
+                .is_none_or(|roles| roles.iter().any(|role| role == auth_role));
+
+        eligible && !excluded
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        self.marshal_into(&mut options);
+        options
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        set_option(options, OPTION_ACKNOWLEDGE, self.acknowledge.map(Value::Bool));
+        set_option(options, OPTION_EXCLUDE_ME, self.exclude_me.map(Value::Bool));
+        set_option(options, OPTION_EXCLUDE, self.exclude.as_deref().map(int_list));
+        set_option(
+            options,
+            OPTION_EXCLUDE_AUTHID,
+            self.exclude_authid.as_deref().map(str_list),
+        );
+        set_option(
+            options,
+            OPTION_EXCLUDE_AUTHROLE,
+            self.exclude_authrole.as_deref().map(str_list),
+        );
+        set_option(options, OPTION_ELIGIBLE, self.eligible.as_deref().map(int_list));
+        set_option(
+            options,
+            OPTION_ELIGIBLE_AUTHID,
+            self.eligible_authid.as_deref().map(str_list),
+        );
+        set_option(
+            options,
+            OPTION_ELIGIBLE_AUTHROLE,
+            self.eligible_authrole.as_deref().map(str_list),
+        );
+        set_option(options, OPTION_RETAIN, self.retain.map(Value::Bool));
+        set_option(options, OPTION_DISCLOSE_ME, self.disclose_me.map(Value::Bool));
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Self, Error> {
+        Ok(PublishOptions {
+            acknowledge: optional_bool(options, OPTION_ACKNOWLEDGE)?,
+            exclude_me: optional_bool(options, OPTION_EXCLUDE_ME)?,
+            exclude: optional_int_list(options, OPTION_EXCLUDE)?,
+            exclude_authid: optional_str_list(options, OPTION_EXCLUDE_AUTHID)?,
+            exclude_authrole: optional_str_list(options, OPTION_EXCLUDE_AUTHROLE)?,
+            eligible: optional_int_list(options, OPTION_ELIGIBLE)?,
+            eligible_authid: optional_str_list(options, OPTION_ELIGIBLE_AUTHID)?,
+            eligible_authrole: optional_str_list(options, OPTION_ELIGIBLE_AUTHROLE)?,
+            retain: optional_bool(options, OPTION_RETAIN)?,
+            disclose_me: optional_bool(options, OPTION_DISCLOSE_ME)?,
+        })
+    }
+}
+
+pub fn get_str<'a>(options: &'a HashMap<String, Value>, key: &str) -> Option<&'a str> {
+    match options.get(key) {
+        Some(Value::Str(s)) => Some(s),
+        _ => None,
+    }
+}
+
+// lenient lookup of a boolean flag, anything but true counts as unset
+pub fn is_set(options: &HashMap<String, Value>, key: &str) -> bool {
+    matches!(options.get(key), Some(Value::Bool(true)))
+}
+
+// unset options are removed so that marshalling into an existing dict doesn't leave stale values behind
+fn set_option(options: &mut HashMap<String, Value>, key: &str, value: Option<Value>) {
+    match value {
+        Some(value) => options.insert(key.to_string(), value),
+        None => options.remove(key),
+    };
+}
+
+fn int_list(values: &[i64]) -> Value {
+    Value::List(values.iter().map(|v| Value::Int(*v)).collect())
+}
+
+fn str_list(values: &[String]) -> Value {
+    Value::List(values.iter().map(|v| Value::str(v.clone())).collect())
+}
+
+fn invalid_type(key: &str, value: &Value) -> Error {
+    Error::invalid_argument(format!("Invalid type for '{key}': {value:?}"))
+}
+
+fn optional_bool(options: &HashMap<String, Value>, key: &str) -> Result<Option<bool>, Error> {
+    match options.get(key) {
+        Some(Value::Bool(b)) => Ok(Some(*b)),
+        Some(v) => Err(invalid_type(key, v)),
+        None => Ok(None),
+    }
+}
+
+fn optional_int(options: &HashMap<String, Value>, key: &str) -> Result<Option<i64>, Error> {
+    match options.get(key) {
+        Some(Value::Int(i)) => Ok(Some(*i)),
+        Some(v) => Err(invalid_type(key, v)),
+        None => Ok(None),
+    }
+}
+
+fn optional_int_list(options: &HashMap<String, Value>, key: &str) -> Result<Option<Vec<i64>>, Error> {
+    match options.get(key) {
+        Some(Value::List(values)) => values
+            .iter()
+            .map(|v| match v {
+                Value::Int(i) => Ok(*i),
+                v => Err(invalid_type(key, v)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        Some(v) => Err(invalid_type(key, v)),
+        None => Ok(None),
+    }
+}
+
+fn optional_str_list(options: &HashMap<String, Value>, key: &str) -> Result<Option<Vec<String>>, Error> {
+    match options.get(key) {
+        Some(Value::List(values)) => values
+            .iter()
+            .map(|v| match v {
+                Value::Str(s) => Ok(s.clone()),
+                v => Err(invalid_type(key, v)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        Some(v) => Err(invalid_type(key, v)),
+        None => Ok(None),
+    }
+}
+
+fn optional_parsed<P: FromStr<Err = Error>>(options: &HashMap<String, Value>, key: &str) -> Result<Option<P>, Error> {
+    match options.get(key) {
+        Some(Value::Str(s)) => s.parse().map(Some),
+        Some(v) => Err(invalid_type(key, v)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,227 @@
+use crate::messages::types::{ByteArray, Error, Value};
+use crate::serializers::serializer::Serializer;
+use std::collections::HashMap;
+
+pub const OPTION_PPT_SCHEME: &str = "ppt_scheme";
+pub const OPTION_PPT_SERIALIZER: &str = "ppt_serializer";
+pub const OPTION_PPT_CIPHER: &str = "ppt_cipher";
+pub const OPTION_PPT_KEYID: &str = "ppt_keyid";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassthruOptions {
+    pub scheme: String,
+    pub serializer: Option<String>,
+    pub cipher: Option<String>,
+    pub keyid: Option<String>,
+}
+
+impl PassthruOptions {
+    pub fn new(scheme: &str) -> Self {
+        PassthruOptions {
+            scheme: scheme.to_string(),
+            serializer: None,
+            cipher: None,
+            keyid: None,
+        }
+    }
+
+    pub fn with_serializer(mut self, serializer: &str) -> Self {
+        self.serializer = Some(serializer.to_string());
+        self
+    }
+
+    pub fn with_cipher(mut self, cipher: &str) -> Self {
+        self.cipher = Some(cipher.to_string());
+        self
+    }
+
+    pub fn with_keyid(mut self, keyid: &str) -> Self {
+        self.keyid = Some(keyid.to_string());
+        self
+    }
+
+    pub fn marshal_into(&self, options: &mut HashMap<String, Value>) {
+        options.insert(OPTION_PPT_SCHEME.to_string(), Value::str(self.scheme.clone()));
+
+        let optional = [
+            (OPTION_PPT_SERIALIZER, &self.serializer),
+            (OPTION_PPT_CIPHER, &self.cipher),
+            (OPTION_PPT_KEYID, &self.keyid),
+        ];
+
+        for (key, value) in optional {
+            match value {
+                Some(value) => options.insert(key.to_string(), Value::str(value.clone())),
+                None => options.remove(key),
+            };
+        }
+    }
+
+    pub fn parse(options: &HashMap<String, Value>) -> Result<Option<Self>, Error> {
+        let scheme = match options.get(OPTION_PPT_SCHEME) {
+            Some(Value::Str(s)) => s,
+            Some(v) => {
+                return Err(Error::invalid_argument(format!(
+                    "Invalid type for '{OPTION_PPT_SCHEME}': {v:?}"
+                )));
+            }
+            None => return Ok(None),
+        };
+
+        Ok(Some(PassthruOptions {
+            scheme: scheme.clone(),
+            serializer: optional_str(options, OPTION_PPT_SERIALIZER)?,
+            cipher: optional_str(options, OPTION_PPT_CIPHER)?,
+            keyid: optional_str(options, OPTION_PPT_KEYID)?,
+        }))
+    }
+
+    pub fn remove_from(options: &mut HashMap<String, Value>) {
+        for key in [
+            OPTION_PPT_SCHEME,
+            OPTION_PPT_SERIALIZER,
+            OPTION_PPT_CIPHER,
+            OPTION_PPT_KEYID,
+        ] {
+            options.remove(key);
+        }
+    }
+
+    // routers must pass the passthru options along unmodified
+    pub fn forward(from: &HashMap<String, Value>, to: &mut HashMap<String, Value>) {
+        for key in [
+            OPTION_PPT_SCHEME,
+            OPTION_PPT_SERIALIZER,
+            OPTION_PPT_CIPHER,
+            OPTION_PPT_KEYID,
+        ] {
+            if let Some(value) = from.get(key) {
+                to.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+fn optional_str(options: &HashMap<String, Value>, key: &str) -> Result<Option<String>, Error> {
+    match options.get(key) {
+        Some(Value::Str(s)) => Ok(Some(s.clone())),
+        Some(v) => Err(Error::invalid_argument(format!("Invalid type for '{key}': {v:?}"))),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Arguments {
+        args: Option<Vec<Value>>,
+        kwargs: Option<HashMap<String, Value>>,
+    },
+    Passthru {
+        options: PassthruOptions,
+        payload: Vec<u8>,
+    },
+}
+
+pub type PayloadFields<'a> = (
+    &'a HashMap<String, Value>,
+    &'a Option<Vec<Value>>,
+    &'a Option<HashMap<String, Value>>,
+);
+
+pub type PayloadFieldsMut<'a> = (
+    &'a mut HashMap<String, Value>,
+    &'a mut Option<Vec<Value>>,
+    &'a mut Option<HashMap<String, Value>>,
+);
+
+pub trait PayloadMessage {
+    fn payload_fields(&self) -> PayloadFields<'_>;
+    fn payload_fields_mut(&mut self) -> PayloadFieldsMut<'_>;
+
+    fn payload(&self) -> Result<Payload, Error> {
+        let (options, args, kwargs) = self.payload_fields();
+        match PassthruOptions::parse(options)? {
+            None => Ok(Payload::Arguments {
+                args: args.clone(),
+                kwargs: kwargs.clone(),
+            }),
+            Some(passthru) => match (args.as_deref(), kwargs) {
+                (Some([Value::Bytes(payload)]), None) => Ok(Payload::Passthru {
+                    options: passthru,
+                    payload: payload.to_vec(),
+                }),
+                _ => Err(Error::invalid_argument(
+                    "passthru payload must be a single binary argument without keyword arguments",
+                )),
+            },
+        }
+    }
+
+    fn set_payload(&mut self, payload: Payload) {
+        let (options, args, kwargs) = self.payload_fields_mut();
+        match payload {
+            Payload::Arguments {
+                args: new_args,
+                kwargs: new_kwargs,
+            } => {
+                PassthruOptions::remove_from(options);
+                *args = new_args;
+                *kwargs = new_kwargs;
+            }
+            Payload::Passthru {
+                options: passthru,
+                payload,
+            } => {
+                passthru.marshal_into(options);
+                *args = Some(vec![Value::Bytes(ByteArray(payload))]);
+                *kwargs = None;
+            }
+        }
+    }
+}
+
+pub fn encode_payload(
+    serializer: &dyn Serializer,
+    options: PassthruOptions,
+    args: Option<Vec<Value>>,
+    kwargs: Option<HashMap<String, Value>>,
+) -> Result<Payload, Error> {
+    let mut inner = HashMap::new();
+    if let Some(args) = args {
+        inner.insert("args".to_string(), Value::List(args));
+    }
+
+    if let Some(kwargs) = kwargs {
+        inner.insert("kwargs".to_string(), Value::Dict(kwargs));
+    }
+
+    Ok(Payload::Passthru {
+        options,
+        payload: serializer.serialize_value(&Value::Dict(inner))?,
+    })
+}
+
+pub fn decode_payload(serializer: &dyn Serializer, payload: &[u8]) -> Result<Payload, Error> {
+    let mut inner = match serializer.deserialize_value(payload)? {
+        Value::Dict(inner) => inner,
+        v => {
+            return Err(Error::invalid_argument(format!(
+                "passthru payload must be a dict, got {v:?}"
+            )));
+        }
+    };
+
+    let args = match inner.remove("args") {
+        Some(Value::List(args)) => Some(args),
+        Some(Value::Null) | None => None,
+        Some(v) => return Err(Error::invalid_argument(format!("Invalid type for 'args': {v:?}"))),
+    };
+
+    let kwargs = match inner.remove("kwargs") {
+        Some(Value::Dict(kwargs)) => Some(kwargs),
+        Some(Value::Null) | None => None,
+        Some(v) => return Err(Error::invalid_argument(format!("Invalid type for 'kwargs': {v:?}"))),
+    };
+
+    Ok(Payload::Arguments { args, kwargs })
+}
@@ -1,7 +1,10 @@
 use crate::messages;
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 
@@ -39,17 +42,51 @@ impl Message for Subscribe {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Int(request_id), Value::Dict(options), Value::Str(topic)] = &data[..] {
-            Ok(Box::new(Subscribe {
-                request_id: *request_id,
-                options: options.clone(),
-                topic: topic.clone(),
-            }))
+        if let Ok([_, Value::Int(request_id), Value::Dict(options), Value::Str(topic)]) = <[Value; 4]>::try_from(data) {
+            Ok(Subscribe {
+                request_id,
+                options,
+                topic,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Subscribe {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(4))?;
+        seq.serialize_element(&MESSAGE_TYPE_SUBSCRIBE)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.serialize_element(&self.options)?;
+        seq.serialize_element(&self.topic)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Subscribe {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_SUBSCRIBE;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let request_id = fields.next()?;
+        let options = fields.next()?;
+        let topic = fields.next()?;
+
+        Ok(Subscribe {
+            request_id,
+            options,
+            topic,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Subscribe {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
@@ -1,7 +1,10 @@
 use crate::messages;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
 
@@ -37,16 +40,47 @@ impl Message for Subscribed {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Int(request_id), Value::Int(subscription_id)] = &data[..] {
-            Ok(Box::new(Subscribed {
-                request_id: *request_id,
-                subscription_id: *subscription_id,
-            }))
+        if let Ok([_, Value::Int(request_id), Value::Int(subscription_id)]) = <[Value; 3]>::try_from(data) {
+            Ok(Subscribed {
+                request_id,
+                subscription_id,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Subscribed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_SUBSCRIBED)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.serialize_element(&self.subscription_id)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Subscribed {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_SUBSCRIBED;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let request_id = fields.next()?;
+        let subscription_id = fields.next()?;
+
+        Ok(Subscribed {
+            request_id,
+            subscription_id,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Subscribed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
@@ -1,11 +1,44 @@
-use serde::{Deserialize, Serialize};
+use crate::messages::abort::Abort;
+use crate::messages::error::Error as ErrorMessage;
+use base64::{Engine as _, engine::general_purpose};
+use serde::de::{self, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-#[serde(transparent)]
-pub struct ByteArray(#[serde(with = "serde_bytes")] pub Vec<u8>);
+// the WAMP spec transports binary in JSON as a string of a NUL byte followed by the base64 encoded data
+const BINARY_PREFIX: char = '\0';
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ByteArray(pub Vec<u8>);
+
+// formats without a binary type (i.e. JSON) report themselves as human readable
+impl Serialize for ByteArray {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&format!("{BINARY_PREFIX}{}", general_purpose::STANDARD.encode(&self.0)))
+        } else {
+            serde_bytes::serialize(&self.0, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Bytes(bytes) => Ok(bytes),
+            v => Err(de::Error::custom(format!("expected binary, got {v:?}"))),
+        }
+    }
+}
+
+fn decode_binary<E: de::Error>(encoded: &str) -> Result<Value, E> {
+    general_purpose::STANDARD
+        .decode(encoded)
+        .map(|bytes| Value::Bytes(ByteArray(bytes)))
+        .map_err(|e| E::custom(format!("failed to decode binary string: {e}")))
+}
 
 impl Deref for ByteArray {
     type Target = Vec<u8>;
@@ -21,7 +54,7 @@ impl DerefMut for ByteArray {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(untagged)]
 pub enum Value {
     Null,
@@ -60,6 +93,102 @@ impl Value {
     }
 }
 
+// Deserialize by the wire type so that binary data is never mistaken for a string
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_any(ValueVisitor { human_readable })
+    }
+}
+
+struct ValueVisitor {
+    // strings carry binary data in human readable formats
+    human_readable: bool,
+}
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a WAMP value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    // WAMP integers are signed, a float would silently lose precision
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Value::Int(v)),
+            Err(_) => Err(E::invalid_value(
+                Unexpected::Unsigned(v),
+                &"an integer within the i64 range",
+            )),
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        match v.strip_prefix(BINARY_PREFIX) {
+            Some(encoded) if self.human_readable => decode_binary(encoded),
+            _ => Ok(Value::Str(v.to_string())),
+        }
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        match v.strip_prefix(BINARY_PREFIX) {
+            Some(encoded) if self.human_readable => decode_binary(encoded),
+            _ => Ok(Value::Str(v)),
+        }
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(ByteArray(v.to_vec())))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(ByteArray(v)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut list = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            list.push(value);
+        }
+
+        Ok(Value::List(list))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut dict = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            dict.insert(key, value);
+        }
+
+        Ok(Value::Dict(dict))
+    }
+}
+
 // Implement From for types
 impl From<i64> for Value {
     fn from(val: i64) -> Self {
@@ -115,21 +244,121 @@ impl From<ByteArray> for Value {
     }
 }
 
-#[derive(Debug)]
-pub struct Error {
-    pub message: String,
+pub const ERROR_PROTOCOL_VIOLATION: &str = "wamp.error.protocol_violation";
+pub const ERROR_AUTHENTICATION_FAILED: &str = "wamp.error.authentication_failed";
+pub const ERROR_NOT_AUTHORIZED: &str = "wamp.error.not_authorized";
+pub const ERROR_INVALID_ARGUMENT: &str = "wamp.error.invalid_argument";
+pub const ERROR_NO_AUTH_METHOD: &str = "wamp.error.no_auth_method";
+pub const ERROR_NO_SUCH_REALM: &str = "wamp.error.no_such_realm";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    ProtocolViolation(String),
+    Serialization(String),
+    Authentication(String),
+    NotAuthorized(String),
+    InvalidArgument(String),
+    InvalidState(String),
+    Transport(String),
+    Application { uri: String, message: String },
 }
 
 impl Error {
+    #[deprecated(
+        note = "use a typed constructor such as Error::protocol_violation, and message() instead of the field"
+    )]
     pub fn new<T: Into<String>>(msg: T) -> Self {
-        Error { message: msg.into() }
+        Error::ProtocolViolation(msg.into())
+    }
+
+    pub fn protocol_violation<T: Into<String>>(msg: T) -> Self {
+        Error::ProtocolViolation(msg.into())
+    }
+
+    pub fn serialization<T: Into<String>>(msg: T) -> Self {
+        Error::Serialization(msg.into())
+    }
+
+    pub fn authentication<T: Into<String>>(msg: T) -> Self {
+        Error::Authentication(msg.into())
+    }
+
+    pub fn not_authorized<T: Into<String>>(msg: T) -> Self {
+        Error::NotAuthorized(msg.into())
+    }
+
+    pub fn invalid_argument<T: Into<String>>(msg: T) -> Self {
+        Error::InvalidArgument(msg.into())
+    }
+
+    pub fn invalid_state<T: Into<String>>(msg: T) -> Self {
+        Error::InvalidState(msg.into())
+    }
+
+    pub fn transport<T: Into<String>>(msg: T) -> Self {
+        Error::Transport(msg.into())
+    }
+
+    pub fn application<U: Into<String>, T: Into<String>>(uri: U, msg: T) -> Self {
+        Error::Application {
+            uri: uri.into(),
+            message: msg.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::ProtocolViolation(message)
+            | Error::Serialization(message)
+            | Error::Authentication(message)
+            | Error::NotAuthorized(message)
+            | Error::InvalidArgument(message)
+            | Error::InvalidState(message)
+            | Error::Transport(message)
+            | Error::Application { message, .. } => message,
+        }
+    }
+
+    // the URI to use when the error is reported to the peer in an ABORT or ERROR message
+    pub fn uri(&self) -> &str {
+        match self {
+            Error::ProtocolViolation(_) | Error::Serialization(_) | Error::InvalidState(_) | Error::Transport(_) => {
+                ERROR_PROTOCOL_VIOLATION
+            }
+            Error::Authentication(_) => ERROR_AUTHENTICATION_FAILED,
+            Error::NotAuthorized(_) => ERROR_NOT_AUTHORIZED,
+            Error::InvalidArgument(_) => ERROR_INVALID_ARGUMENT,
+            Error::Application { uri, .. } => uri,
+        }
+    }
+
+    pub fn to_abort(&self) -> Abort {
+        let mut details = HashMap::new();
+        details.insert("message".to_string(), Value::str(self.message()));
+
+        Abort {
+            details,
+            reason: self.uri().to_string(),
+            args: None,
+            kwargs: None,
+        }
+    }
+
+    pub fn to_error_message(&self, message_type: Value, request_id: i64) -> ErrorMessage {
+        ErrorMessage {
+            message_type,
+            request_id,
+            options: HashMap::new(),
+            uri: self.uri().to_string(),
+            args: Some(vec![Value::str(self.message())]),
+            kwargs: None,
+        }
     }
 }
 
-// Implement Display so errors print nicely
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "MyError: {}", self.message)
+        write!(f, "{}: {}", self.uri(), self.message())
     }
 }
 
@@ -1,7 +1,10 @@
 use crate::messages;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
 
@@ -37,16 +40,47 @@ impl Message for Unregister {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Int(request_id), Value::Int(registration_id)] = &data[..] {
-            Ok(Box::new(Unregister {
-                request_id: *request_id,
-                registration_id: *registration_id,
-            }))
+        if let Ok([_, Value::Int(request_id), Value::Int(registration_id)]) = <[Value; 3]>::try_from(data) {
+            Ok(Unregister {
+                request_id,
+                registration_id,
+            })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Unregister {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_UNREGISTER)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.serialize_element(&self.registration_id)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Unregister {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_UNREGISTER;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let request_id = fields.next()?;
+        let registration_id = fields.next()?;
+
+        Ok(Unregister {
+            request_id,
+            registration_id,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Unregister {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
@@ -1,7 +1,10 @@
 use crate::messages;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
 
@@ -32,15 +35,39 @@ impl Message for Unsubscribed {
         vec![MESSAGE_TYPE_UNSUBSCRIBED, Value::Int(self.request_id)]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Int(request_id)] = &data[..] {
-            Ok(Box::new(Unsubscribed {
-                request_id: *request_id,
-            }))
+        if let Ok([_, Value::Int(request_id)]) = <[Value; 2]>::try_from(data) {
+            Ok(Unsubscribed { request_id })
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Unsubscribed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        seq.serialize_element(&MESSAGE_TYPE_UNSUBSCRIBED)?;
+        seq.serialize_element(&self.request_id)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Unsubscribed {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_UNSUBSCRIBED;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let request_id = fields.next()?;
+
+        Ok(Unsubscribed { request_id })
+    }
+}
+
+impl<'de> Deserialize<'de> for Unsubscribed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
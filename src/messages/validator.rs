@@ -9,12 +9,26 @@ pub struct ValidationSpec {
 
 impl ValidationSpec {
     pub fn err_invalid_message(&self) -> Error {
-        Error::new(format!("{} received invalid message format", self.name))
+        Error::protocol_violation(format!("{} received invalid message format", self.name))
     }
 
     pub fn validate_message(&self, data: &[Value]) -> Result<(), Error> {
         sanity_check(data, self)
     }
+
+    pub fn err_too_short(&self, length: usize) -> Error {
+        Error::protocol_violation(format!(
+            "unexpected message length for {}: must be at least {}, but was {}",
+            self.name, self.min_length, length
+        ))
+    }
+
+    pub fn err_too_long(&self, length: usize) -> Error {
+        Error::protocol_violation(format!(
+            "unexpected message length for {}, must be at most {}, but was {}",
+            self.name, self.max_length, length
+        ))
+    }
 }
 
 pub fn get_type<T>(_: &T) -> String {
@@ -23,23 +37,9 @@ pub fn get_type<T>(_: &T) -> String {
 
 fn sanity_check(wamp_msg: &[Value], spec: &ValidationSpec) -> Result<(), Error> {
     if wamp_msg.len() < spec.min_length {
-        let message = format!(
-            "unexpected message length for {}: must be at least {}, but was {}",
-            spec.name,
-            spec.min_length,
-            wamp_msg.len()
-        );
-
-        Err(Error::new(message))
+        Err(spec.err_too_short(wamp_msg.len()))
     } else if wamp_msg.len() > spec.max_length {
-        let message: String = format!(
-            "unexpected message length for {}, must be at most {}, but was {}",
-            spec.name,
-            spec.max_length,
-            wamp_msg.len()
-        );
-
-        Err(Error::new(message))
+        Err(spec.err_too_long(wamp_msg.len()))
     } else {
         Ok(())
     }
@@ -0,0 +1,150 @@
+use crate::messages::abort::Abort;
+use crate::messages::authenticate::Authenticate;
+use crate::messages::call::Call;
+use crate::messages::cancel::Cancel;
+use crate::messages::challenge::Challenge;
+use crate::messages::error::Error as ErrorMessage;
+use crate::messages::event::Event;
+use crate::messages::goodbye::Goodbye;
+use crate::messages::hello::Hello;
+use crate::messages::interrupt::Interrupt;
+use crate::messages::invocation::Invocation;
+use crate::messages::message::{Message, ReadFields, read_message};
+use crate::messages::publish::Publish;
+use crate::messages::published::Published;
+use crate::messages::register::Register;
+use crate::messages::registered::Registered;
+use crate::messages::result::Result_;
+use crate::messages::subscribe::Subscribe;
+use crate::messages::subscribed::Subscribed;
+use crate::messages::types::{Error, Value};
+use crate::messages::unregister::Unregister;
+use crate::messages::unregistered::Unregistered;
+use crate::messages::unsubscribe::Unsubscribe;
+use crate::messages::unsubscribed::Unsubscribed;
+use crate::messages::welcome::Welcome;
+use crate::messages::yield_::Yield;
+use crate::serializers::helpers::to_message;
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::Any;
+use std::fmt;
+
+macro_rules! wamp_message {
+    ($($variant:ident($message:ty)),* $(,)?) => {
+        #[derive(Debug)]
+        pub enum WampMessage {
+            $($variant($message),)*
+        }
+
+        impl WampMessage {
+            pub fn as_message(&self) -> &dyn Message {
+                match self {
+                    $(WampMessage::$variant(message) => message,)*
+                }
+            }
+
+            pub fn into_boxed(self) -> Box<dyn Message> {
+                match self {
+                    $(WampMessage::$variant(message) => Box::new(message),)*
+                }
+            }
+        }
+
+        impl Serialize for WampMessage {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    $(WampMessage::$variant(message) => message.serialize(serializer),)*
+                }
+            }
+        }
+
+        struct WampMessageVisitor;
+
+        impl<'de> Visitor<'de> for WampMessageVisitor {
+            type Value = WampMessage;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a wamp message array")
+            }
+
+            // the type element picks the message, which then reads its own fields
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<WampMessage, A::Error> {
+                let message_type = match seq.next_element::<Value>()? {
+                    Some(message_type) => message_type,
+                    None => return Err(A::Error::custom("received empty wamp message array")),
+                };
+
+                $(
+                    if message_type == <$message as ReadFields>::MESSAGE_TYPE {
+                        return read_message(seq).map(WampMessage::$variant);
+                    }
+                )*
+
+                Err(A::Error::custom(format!(
+                    "received invalid wamp message of type {message_type:?}"
+                )))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for WampMessage {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_seq(WampMessageVisitor)
+            }
+        }
+
+        $(
+            impl From<$message> for WampMessage {
+                fn from(message: $message) -> Self {
+                    WampMessage::$variant(message)
+                }
+            }
+        )*
+    };
+}
+
+wamp_message! {
+    Hello(Hello),
+    Welcome(Welcome),
+    Abort(Abort),
+    Challenge(Challenge),
+    Authenticate(Authenticate),
+    Goodbye(Goodbye),
+    Error(ErrorMessage),
+    Publish(Publish),
+    Published(Published),
+    Subscribe(Subscribe),
+    Subscribed(Subscribed),
+    Unsubscribe(Unsubscribe),
+    Unsubscribed(Unsubscribed),
+    Event(Event),
+    Call(Call),
+    Cancel(Cancel),
+    Result(Result_),
+    Register(Register),
+    Registered(Registered),
+    Unregister(Unregister),
+    Unregistered(Unregistered),
+    Invocation(Invocation),
+    Interrupt(Interrupt),
+    Yield(Yield),
+}
+
+// lets the enum be passed wherever a message is expected, as_any exposes the wrapped message
+impl Message for WampMessage {
+    fn as_any(&self) -> &dyn Any {
+        self.as_message().as_any()
+    }
+
+    fn message_type(&self) -> Value {
+        self.as_message().message_type()
+    }
+
+    fn marshal(&self) -> Vec<Value> {
+        self.as_message().marshal()
+    }
+
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
+        to_message(data)
+    }
+}
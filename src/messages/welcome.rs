@@ -1,8 +1,11 @@
 use crate::messages;
+use serde::de::SeqAccess;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::HashMap;
 
-use messages::message::Message;
+use messages::message::{Message, MessageFields, ReadFields, deserialize_message};
 use messages::types::{Error, Value};
 use messages::validator::ValidationSpec;
 
@@ -41,39 +44,94 @@ impl Message for Welcome {
         ]
     }
 
-    fn parse(data: Vec<Value>) -> Result<Box<dyn Message>, Error> {
+    fn parse(data: Vec<Value>) -> Result<Self, Error> {
         VALIDATION_SPEC.validate_message(&data)?;
 
-        if let [_, Value::Int(session_id), Value::Dict(details)] = &data[..] {
-            let realm = match details.get("realm") {
-                Some(Value::Str(s)) => s,
-                Some(v) => return Err(Error::new(format!("Invalid type for 'realm': {v:?}"))),
-                // some routers don't return realm in welcome and the one sent in HELLO
-                // is assumed to be the one.
-                None => "",
-            };
-
-            let authid = match details.get("authid") {
-                Some(Value::Str(s)) => s,
-                Some(v) => return Err(Error::new(format!("Invalid type for 'authid': {v:?}"))),
-                None => return Err(Error::new("Missing field: 'authid'")),
-            };
-
-            let auth_role = match details.get("authrole") {
-                Some(Value::Str(s)) => s,
-                Some(v) => return Err(Error::new(format!("Invalid type for 'authrole': {v:?}"))),
-                None => return Err(Error::new("Missing field: 'authrole'")),
-            };
-
-            Ok(Box::new(Welcome {
-                session_id: *session_id,
-                realm: realm.to_string(),
-                authid: authid.to_string(),
-                auth_role: auth_role.to_string(),
-                details: details.clone(),
-            }))
+        if let Ok([_, Value::Int(session_id), Value::Dict(details)]) = <[Value; 3]>::try_from(data) {
+            Welcome::from_details(session_id, details)
         } else {
             Err(VALIDATION_SPEC.err_invalid_message())
         }
     }
 }
+
+impl Serialize for Welcome {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&MESSAGE_TYPE_WELCOME)?;
+        seq.serialize_element(&self.session_id)?;
+        seq.serialize_element(&self.details)?;
+        seq.end()
+    }
+}
+
+impl ReadFields for Welcome {
+    const MESSAGE_TYPE: Value = MESSAGE_TYPE_WELCOME;
+    const SPEC: &'static ValidationSpec = &VALIDATION_SPEC;
+
+    fn read_fields<'de, A: SeqAccess<'de>>(fields: &mut MessageFields<A>) -> Result<Self, A::Error> {
+        let session_id = fields.next()?;
+        let details = fields.next()?;
+
+        Welcome::from_details(session_id, details).map_err(|e| fields.invalid(e))
+    }
+}
+
+impl<'de> Deserialize<'de> for Welcome {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_message(deserializer)
+    }
+}
+
+impl Welcome {
+    pub fn new(
+        session_id: i64,
+        realm: &str,
+        authid: &str,
+        auth_role: &str,
+        details: HashMap<String, Value>,
+    ) -> Welcome {
+        let mut details = details;
+        details.insert("realm".to_string(), Value::str(realm));
+        details.insert("authid".to_string(), Value::str(authid));
+        details.insert("authrole".to_string(), Value::str(auth_role));
+
+        Welcome {
+            session_id,
+            realm: realm.to_string(),
+            authid: authid.to_string(),
+            auth_role: auth_role.to_string(),
+            details,
+        }
+    }
+
+    fn from_details(session_id: i64, details: HashMap<String, Value>) -> Result<Welcome, Error> {
+        let realm = match details.get("realm") {
+            Some(Value::Str(s)) => s,
+            Some(v) => return Err(Error::protocol_violation(format!("Invalid type for 'realm': {v:?}"))),
+            // some routers don't return realm in welcome and the one sent in HELLO
+            // is assumed to be the one.
+            None => "",
+        };
+
+        let authid = match details.get("authid") {
+            Some(Value::Str(s)) => s,
+            Some(v) => return Err(Error::protocol_violation(format!("Invalid type for 'authid': {v:?}"))),
+            None => return Err(Error::protocol_violation("Missing field: 'authid'")),
+        };
+
+        let auth_role = match details.get("authrole") {
+            Some(Value::Str(s)) => s,
+            Some(v) => return Err(Error::protocol_violation(format!("Invalid type for 'authrole': {v:?}"))),
+            None => return Err(Error::protocol_violation("Missing field: 'authrole'")),
+        };
+
+        Ok(Welcome {
+            session_id,
+            realm: realm.to_string(),
+            authid: authid.to_string(),
+            auth_role: auth_role.to_string(),
+            details,
+        })
+    }
+}
@@ -0,0 +1,220 @@
+use crate::messages::types::{Error, Value};
+use std::collections::HashMap;
+
+pub const ROLE_CALLER: &str = "caller";
+pub const ROLE_CALLEE: &str = "callee";
+pub const ROLE_PUBLISHER: &str = "publisher";
+pub const ROLE_SUBSCRIBER: &str = "subscriber";
+pub const ROLE_DEALER: &str = "dealer";
+pub const ROLE_BROKER: &str = "broker";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Features {
+    pub progressive_call_results: bool,
+    pub progressive_call_invocations: bool,
+    pub call_canceling: bool,
+    pub call_timeout: bool,
+    pub caller_identification: bool,
+    pub pattern_based_registration: bool,
+    pub shared_registration: bool,
+    pub publisher_exclusion: bool,
+    pub publisher_identification: bool,
+    pub subscriber_blackwhite_listing: bool,
+    pub pattern_based_subscription: bool,
+    pub payload_passthru_mode: bool,
+}
+
+impl Features {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn flags(&self) -> [(&'static str, bool); 12] {
+        [
+            ("progressive_call_results", self.progressive_call_results),
+            ("progressive_call_invocations", self.progressive_call_invocations),
+            ("call_canceling", self.call_canceling),
+            ("call_timeout", self.call_timeout),
+            ("caller_identification", self.caller_identification),
+            ("pattern_based_registration", self.pattern_based_registration),
+            ("shared_registration", self.shared_registration),
+            ("publisher_exclusion", self.publisher_exclusion),
+            ("publisher_identification", self.publisher_identification),
+            ("subscriber_blackwhite_listing", self.subscriber_blackwhite_listing),
+            ("pattern_based_subscription", self.pattern_based_subscription),
+            ("payload_passthru_mode", self.payload_passthru_mode),
+        ]
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "progressive_call_results" => Some(&mut self.progressive_call_results),
+            "progressive_call_invocations" => Some(&mut self.progressive_call_invocations),
+            "call_canceling" => Some(&mut self.call_canceling),
+            "call_timeout" => Some(&mut self.call_timeout),
+            "caller_identification" => Some(&mut self.caller_identification),
+            "pattern_based_registration" => Some(&mut self.pattern_based_registration),
+            "shared_registration" => Some(&mut self.shared_registration),
+            "publisher_exclusion" => Some(&mut self.publisher_exclusion),
+            "publisher_identification" => Some(&mut self.publisher_identification),
+            "subscriber_blackwhite_listing" => Some(&mut self.subscriber_blackwhite_listing),
+            "pattern_based_subscription" => Some(&mut self.pattern_based_subscription),
+            "payload_passthru_mode" => Some(&mut self.payload_passthru_mode),
+            _ => None,
+        }
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        self.flags()
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| (name.to_string(), Value::Bool(true)))
+            .collect()
+    }
+
+    pub fn parse(features: &HashMap<String, Value>) -> Self {
+        let mut parsed = Features::new();
+        for (name, value) in features {
+            // unknown features and non-boolean values are ignored
+            if let (Some(flag), Value::Bool(enabled)) = (parsed.flag_mut(name), value) {
+                *flag = *enabled;
+            }
+        }
+
+        parsed
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Roles {
+    roles: HashMap<String, Features>,
+}
+
+impl Roles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client() -> Self {
+        Roles::new()
+            .with_role(
+                ROLE_CALLER,
+                Features {
+                    progressive_call_results: true,
+                    progressive_call_invocations: true,
+                    call_canceling: true,
+                    call_timeout: true,
+                    ..Default::default()
+                },
+            )
+            .with_role(
+                ROLE_CALLEE,
+                Features {
+                    progressive_call_results: true,
+                    progressive_call_invocations: true,
+                    call_canceling: true,
+                    pattern_based_registration: true,
+                    shared_registration: true,
+                    ..Default::default()
+                },
+            )
+            .with_role(
+                ROLE_PUBLISHER,
+                Features {
+                    publisher_exclusion: true,
+                    ..Default::default()
+                },
+            )
+            .with_role(
+                ROLE_SUBSCRIBER,
+                Features {
+                    pattern_based_subscription: true,
+                    ..Default::default()
+                },
+            )
+    }
+
+    pub fn router() -> Self {
+        Roles::new()
+            .with_role(
+                ROLE_DEALER,
+                Features {
+                    progressive_call_results: true,
+                    progressive_call_invocations: true,
+                    call_canceling: true,
+                    call_timeout: true,
+                    pattern_based_registration: true,
+                    shared_registration: true,
+                    payload_passthru_mode: true,
+                    ..Default::default()
+                },
+            )
+            .with_role(
+                ROLE_BROKER,
+                Features {
+                    publisher_exclusion: true,
+                    subscriber_blackwhite_listing: true,
+                    pattern_based_subscription: true,
+                    payload_passthru_mode: true,
+                    ..Default::default()
+                },
+            )
+    }
+
+    pub fn with_role(mut self, role: &str, features: Features) -> Self {
+        self.roles.insert(role.to_string(), features);
+        self
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.contains_key(role)
+    }
+
+    pub fn features(&self, role: &str) -> Option<&Features> {
+        self.roles.get(role)
+    }
+
+    pub fn supports<F>(&self, role: &str, feature: F) -> bool
+    where
+        F: Fn(&Features) -> bool,
+    {
+        self.roles.get(role).is_some_and(feature)
+    }
+
+    pub fn marshal(&self) -> HashMap<String, Value> {
+        self.roles
+            .iter()
+            .map(|(role, features)| {
+                let mut inner = HashMap::new();
+                inner.insert("features".to_string(), Value::Dict(features.marshal()));
+                (role.clone(), Value::Dict(inner))
+            })
+            .collect()
+    }
+
+    pub fn parse(roles: &HashMap<String, Value>) -> Result<Self, Error> {
+        let mut parsed = Roles::new();
+        for (role, value) in roles {
+            let features = match value {
+                Value::Dict(details) => match details.get("features") {
+                    Some(Value::Dict(features)) => Features::parse(features),
+                    Some(v) => {
+                        return Err(Error::protocol_violation(format!(
+                            "Invalid type for 'features' of '{role}': {v:?}"
+                        )));
+                    }
+                    None => Features::new(),
+                },
+                v => {
+                    return Err(Error::protocol_violation(format!(
+                        "Invalid type for role '{role}': {v:?}"
+                    )));
+                }
+            };
+
+            parsed.roles.insert(role.clone(), features);
+        }
+
+        Ok(parsed)
+    }
+}
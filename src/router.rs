@@ -0,0 +1,496 @@
+use crate::acceptor::Acceptor;
+use crate::authenticators::authenticator::ServerAuthenticator;
+use crate::broker::Broker;
+use crate::clock::Clock;
+use crate::dealer::Dealer;
+use crate::idgen::GlobalScopeIDGenerator;
+use crate::messages::goodbye::Goodbye;
+use crate::messages::message::Message;
+use crate::messages::types::Error;
+use crate::messages::wamp_message::WampMessage;
+use crate::serializers::serializer::Serializer;
+use crate::types::{MessageWithRecipient, SessionDetails};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+const CLOSE_GOODBYE_AND_OUT: &str = "wamp.close.goodbye_and_out";
+const CLOSE_CLOSE_REALM: &str = "wamp.close.close_realm";
+
+#[derive(Debug, Default)]
+pub struct Realm {
+    dealer: Dealer,
+    broker: Broker,
+    sessions: HashMap<i64, SessionDetails>,
+}
+
+impl Realm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.dealer = self.dealer.with_clock(clock);
+        self
+    }
+
+    pub fn dealer(&self) -> &Dealer {
+        &self.dealer
+    }
+
+    pub fn broker(&self) -> &Broker {
+        &self.broker
+    }
+
+    pub fn session(&self, session_id: i64) -> Option<&SessionDetails> {
+        self.sessions.get(&session_id)
+    }
+
+    pub fn session_ids(&self) -> impl Iterator<Item = &i64> {
+        self.sessions.keys()
+    }
+
+    fn attach_session(&mut self, details: SessionDetails) -> Result<(), Error> {
+        self.dealer.add_session(&details)?;
+        self.broker.add_session(&details)?;
+        self.sessions.insert(details.id, details);
+        Ok(())
+    }
+
+    fn detach_session(&mut self, session_id: i64) -> Vec<MessageWithRecipient> {
+        self.sessions.remove(&session_id);
+        self.broker.remove_session(session_id);
+        self.dealer.remove_session(session_id)
+    }
+
+    fn receive_message(&mut self, session_id: i64, msg: WampMessage) -> Result<Vec<MessageWithRecipient>, Error> {
+        match msg {
+            WampMessage::Call(_)
+            | WampMessage::Cancel(_)
+            | WampMessage::Yield(_)
+            | WampMessage::Register(_)
+            | WampMessage::Unregister(_)
+            | WampMessage::Error(_) => self.dealer.receive_message(session_id, msg),
+
+            WampMessage::Publish(_) | WampMessage::Subscribe(_) | WampMessage::Unsubscribe(_) => {
+                self.broker.receive_message(session_id, msg)
+            }
+
+            _ => Err(Error::protocol_violation(format!(
+                "router: received unexpected message of type {:?}",
+                msg.message_type()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Router {
+    realms: HashMap<String, Realm>,
+    sessions: HashMap<i64, String>,
+    // sessions the router sent a GOODBYE to and that are waiting for the reply
+    closing: HashSet<i64>,
+
+    authenticators: Vec<Box<dyn ServerAuthenticator>>,
+    session_idgen: GlobalScopeIDGenerator,
+    // drives call timeouts of every realm, the system clock if unset
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl Router {
+    pub fn new(authenticators: Vec<Box<dyn ServerAuthenticator>>) -> Self {
+        Router {
+            authenticators,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        for realm in self.realms.values_mut() {
+            realm.dealer.set_clock(clock.clone());
+        }
+
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn add_realm(&mut self, name: &str) {
+        let clock = self.clock.clone();
+        self.realms.entry(name.to_string()).or_insert_with(|| match clock {
+            Some(clock) => Realm::new().with_clock(clock),
+            None => Realm::new(),
+        });
+    }
+
+    pub fn remove_realm(&mut self, name: &str) -> Vec<MessageWithRecipient> {
+        let session_ids: Vec<i64> = match self.realms.get(name) {
+            Some(realm) => realm.session_ids().copied().collect(),
+            None => return Vec::new(),
+        };
+
+        let mut messages = Vec::new();
+        for session_id in session_ids {
+            messages.extend(self.close_session(session_id, CLOSE_CLOSE_REALM));
+        }
+
+        self.realms.remove(name);
+        messages
+    }
+
+    pub fn has_realm(&self, name: &str) -> bool {
+        self.realms.contains_key(name)
+    }
+
+    pub fn realm(&self, name: &str) -> Option<&Realm> {
+        self.realms.get(name)
+    }
+
+    pub fn create_acceptor(&self, serializer: Box<dyn Serializer>) -> Acceptor {
+        // session ids are random, so a new one may collide with an attached session
+        let mut session_id = self.session_idgen.next_id();
+        while self.sessions.contains_key(&session_id) {
+            session_id = self.session_idgen.next_id();
+        }
+
+        Acceptor::new(session_id, serializer, self.authenticators.clone())
+            .with_realms(self.realms.keys().cloned().collect())
+    }
+
+    pub fn attach_session(&mut self, details: SessionDetails) -> Result<(), Error> {
+        if self.sessions.contains_key(&details.id) {
+            return Err(Error::invalid_state("cannot attach session twice"));
+        }
+
+        let realm_name = details.realm.clone();
+        let session_id = details.id;
+        match self.realms.get_mut(&realm_name) {
+            Some(realm) => realm.attach_session(details)?,
+            None => {
+                return Err(Error::invalid_state(format!(
+                    "cannot attach session, realm '{realm_name}' does not exist"
+                )));
+            }
+        }
+
+        self.sessions.insert(session_id, realm_name);
+        Ok(())
+    }
+
+    pub fn detach_session(&mut self, session_id: i64) -> Vec<MessageWithRecipient> {
+        self.closing.remove(&session_id);
+        match self.sessions.remove(&session_id) {
+            Some(realm_name) => match self.realms.get_mut(&realm_name) {
+                Some(realm) => realm.detach_session(session_id),
+                None => Vec::new(),
+            },
+            None => Vec::new(),
+        }
+    }
+
+    pub fn close_session(&mut self, session_id: i64, reason: &str) -> Vec<MessageWithRecipient> {
+        if !self.sessions.contains_key(&session_id) {
+            return Vec::new();
+        }
+
+        let mut messages = self.detach_session(session_id);
+        self.closing.insert(session_id);
+        messages.push(MessageWithRecipient::new(goodbye(reason), session_id));
+        messages
+    }
+
+    // time left until the next call times out in any realm
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.realms
+            .values()
+            .filter_map(|realm| realm.dealer.next_timeout())
+            .min()
+    }
+
+    pub fn expire_calls(&mut self) -> Vec<MessageWithRecipient> {
+        self.realms
+            .values_mut()
+            .flat_map(|realm| realm.dealer.expire_calls())
+            .collect()
+    }
+
+    // tears the session down after a failure, reporting the error's URI to the peer
+    pub fn abort_session(&mut self, session_id: i64, error: &Error) -> Vec<MessageWithRecipient> {
+        if !self.sessions.contains_key(&session_id) {
+            return Vec::new();
+        }
+
+        let mut messages = self.detach_session(session_id);
+        messages.push(MessageWithRecipient::new(error.to_abort(), session_id));
+        messages
+    }
+
+    pub fn receive_message(&mut self, session_id: i64, msg: WampMessage) -> Result<Vec<MessageWithRecipient>, Error> {
+        if self.closing.contains(&session_id) {
+            // everything except the GOODBYE reply (or an ABORT) is ignored once the router said goodbye
+            if matches!(msg, WampMessage::Goodbye(_) | WampMessage::Abort(_)) {
+                self.closing.remove(&session_id);
+            }
+
+            return Ok(Vec::new());
+        }
+
+        let realm_name = match self.sessions.get(&session_id) {
+            Some(realm_name) => realm_name,
+            None => {
+                return Err(Error::invalid_state(format!(
+                    "router: session {session_id} is not attached"
+                )));
+            }
+        };
+
+        match msg {
+            WampMessage::Goodbye(_) => {
+                let mut messages = self.detach_session(session_id);
+                messages.push(MessageWithRecipient::new(goodbye(CLOSE_GOODBYE_AND_OUT), session_id));
+
+                return Ok(messages);
+            }
+
+            // the peer is gone, an ABORT is never answered
+            WampMessage::Abort(_) => return Ok(self.detach_session(session_id)),
+
+            _ => {}
+        }
+
+        match self.realms.get_mut(realm_name) {
+            Some(realm) => realm.receive_message(session_id, msg),
+            None => Err(Error::invalid_state(format!(
+                "router: realm '{realm_name}' does not exist"
+            ))),
+        }
+    }
+}
+
+fn goodbye(reason: &str) -> Goodbye {
+    Goodbye {
+        details: HashMap::new(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authenticators::anonymous::AnonymousServerAuthenticator;
+    use crate::clock::ManualClock;
+    use crate::messages::abort::Abort;
+    use crate::messages::call::Call;
+    use crate::messages::hello::Hello;
+    use crate::messages::options::{CallOptions, CancelMode, CancelOptions};
+    use crate::messages::register::Register;
+    use crate::messages::subscribe::Subscribe;
+    use crate::roles::Roles;
+    use crate::serializers::json::JSONSerializer;
+
+    // a router with realm1 and the sessions joined to it through the acceptor
+    fn router(sessions: usize) -> (Router, Vec<i64>) {
+        let mut router = Router::new(vec![Box::new(AnonymousServerAuthenticator::default())]);
+        router.add_realm("realm1");
+
+        let mut session_ids = Vec::new();
+        for _ in 0..sessions {
+            let mut acceptor = router.create_acceptor(Box::new(JSONSerializer {}));
+            let hello = Hello::new("realm1", "", HashMap::new(), Roles::client().marshal(), vec![]);
+            acceptor.receive_message(WampMessage::Hello(hello)).unwrap();
+
+            let details = acceptor.into_session_details().unwrap();
+            session_ids.push(details.id);
+            router.attach_session(details).unwrap();
+        }
+
+        (router, session_ids)
+    }
+
+    fn subscribe(router: &mut Router, session_id: i64) {
+        let subscribe = Subscribe {
+            request_id: 1,
+            options: HashMap::new(),
+            topic: "foo.bar".to_string(),
+        };
+
+        let reply = router.receive_message(session_id, subscribe.into()).unwrap().remove(0);
+        assert!(matches!(reply.message, WampMessage::Subscribed(_)));
+    }
+
+    fn abort() -> WampMessage {
+        Abort {
+            details: HashMap::new(),
+            reason: "wamp.error.system_shutdown".to_string(),
+            args: None,
+            kwargs: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn attach_session() {
+        let (mut router, session_ids) = router(1);
+        let realm = router.realm("realm1").unwrap();
+        assert!(realm.session(session_ids[0]).is_some());
+
+        let details = router.realm("realm1").unwrap().session(session_ids[0]).unwrap().clone();
+        assert!(router.attach_session(details).is_err());
+
+        let details = SessionDetails::new(99, "realm2".to_string(), "".to_string(), "".to_string(), false);
+        assert!(router.attach_session(details).is_err());
+    }
+
+    #[test]
+    fn goodbye_round_trip() {
+        let (mut router, session_ids) = router(2);
+        subscribe(&mut router, session_ids[0]);
+
+        let messages = router
+            .receive_message(session_ids[0], goodbye(CLOSE_CLOSE_REALM).into())
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].recipient, session_ids[0]);
+        assert_eq!(messages[0].message.marshal(), goodbye(CLOSE_GOODBYE_AND_OUT).marshal());
+
+        assert!(router.realm("realm1").unwrap().session(session_ids[0]).is_none());
+        assert!(!router.realm("realm1").unwrap().broker().has_subscription("foo.bar"));
+        assert!(
+            router
+                .receive_message(session_ids[0], goodbye(CLOSE_CLOSE_REALM).into())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn abort_detaches_without_reply() {
+        let (mut router, session_ids) = router(1);
+        subscribe(&mut router, session_ids[0]);
+
+        assert!(router.receive_message(session_ids[0], abort()).unwrap().is_empty());
+        assert!(router.realm("realm1").unwrap().session(session_ids[0]).is_none());
+        assert!(!router.realm("realm1").unwrap().broker().has_subscription("foo.bar"));
+    }
+
+    #[test]
+    fn close_session() {
+        let (mut router, session_ids) = router(2);
+        subscribe(&mut router, session_ids[0]);
+
+        let messages = router.close_session(session_ids[0], CLOSE_CLOSE_REALM);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].recipient, session_ids[0]);
+        assert_eq!(messages[0].message.marshal(), goodbye(CLOSE_CLOSE_REALM).marshal());
+
+        // anything but the GOODBYE reply is ignored while the router waits for it
+        assert!(router.receive_message(session_ids[0], abort()).unwrap().is_empty());
+        assert!(router.receive_message(session_ids[0], abort()).is_err());
+
+        let messages = router.close_session(session_ids[1], CLOSE_CLOSE_REALM);
+        assert_eq!(messages.len(), 1);
+        let subscribe = Subscribe {
+            request_id: 2,
+            options: HashMap::new(),
+            topic: "foo.bar".to_string(),
+        };
+        assert!(
+            router
+                .receive_message(session_ids[1], subscribe.into())
+                .unwrap()
+                .is_empty()
+        );
+        let reply = goodbye(CLOSE_GOODBYE_AND_OUT).into();
+        assert!(router.receive_message(session_ids[1], reply).unwrap().is_empty());
+        assert!(router.receive_message(session_ids[1], abort()).is_err());
+    }
+
+    #[test]
+    fn remove_realm() {
+        let (mut router, session_ids) = router(2);
+        let mut recipients: Vec<i64> = router
+            .remove_realm("realm1")
+            .into_iter()
+            .map(|m| {
+                assert_eq!(m.message.marshal(), goodbye(CLOSE_CLOSE_REALM).marshal());
+                m.recipient
+            })
+            .collect();
+        recipients.sort();
+
+        let mut expected = session_ids.clone();
+        expected.sort();
+        assert_eq!(recipients, expected);
+        assert!(!router.has_realm("realm1"));
+    }
+
+    #[test]
+    fn clock_applies_to_existing_realms() {
+        let (router, session_ids) = router(2);
+        let clock = Arc::new(ManualClock::new());
+        let mut router = router.with_clock(clock.clone());
+        let (callee, caller) = (session_ids[0], session_ids[1]);
+
+        let register = Register {
+            request_id: 1,
+            options: HashMap::new(),
+            procedure: "foo.bar".to_string(),
+        };
+        router.receive_message(callee, register.into()).unwrap();
+
+        let call = Call {
+            request_id: 2,
+            options: CallOptions::new().with_timeout(1000).marshal(),
+            procedure: "foo.bar".to_string(),
+            args: None,
+            kwargs: None,
+        };
+        router.receive_message(caller, call.into()).unwrap();
+        assert_eq!(router.next_timeout(), Some(Duration::from_secs(1)));
+
+        clock.advance(Duration::from_secs(1));
+        let messages = router.expire_calls();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].recipient, callee);
+        match &messages[0].message {
+            WampMessage::Interrupt(interrupt) => {
+                let options = CancelOptions::parse(&interrupt.options).unwrap();
+                assert_eq!(options.mode, Some(CancelMode::KillNoWait));
+            }
+            other => panic!("expected INTERRUPT, got {other:?}"),
+        }
+
+        assert_eq!(messages[1].recipient, caller);
+        match &messages[1].message {
+            WampMessage::Error(error) => assert_eq!((error.request_id, error.uri.as_str()), (2, "wamp.error.timeout")),
+            other => panic!("expected ERROR, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn clock_applies_to_new_realms() {
+        let clock = Arc::new(ManualClock::new());
+        let mut router = Router::new(Vec::new()).with_clock(clock.clone());
+        router.add_realm("realm1");
+
+        let details = SessionDetails::new(1, "realm1".to_string(), "".to_string(), "".to_string(), false);
+        router.attach_session(details).unwrap();
+        let register = Register {
+            request_id: 1,
+            options: HashMap::new(),
+            procedure: "foo.bar".to_string(),
+        };
+        router.receive_message(1, register.into()).unwrap();
+
+        let call = Call {
+            request_id: 2,
+            options: CallOptions::new().with_timeout(1000).marshal(),
+            procedure: "foo.bar".to_string(),
+            args: None,
+            kwargs: None,
+        };
+        router.receive_message(1, call.into()).unwrap();
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(router.next_timeout(), Some(Duration::ZERO));
+        assert_eq!(router.expire_calls().len(), 1);
+    }
+}
@@ -22,40 +22,41 @@ use crate::messages::unregister::{MESSAGE_TYPE_UNREGISTER, Unregister};
 use crate::messages::unregistered::{MESSAGE_TYPE_UNREGISTERED, Unregistered};
 use crate::messages::unsubscribe::{MESSAGE_TYPE_UNSUBSCRIBE, Unsubscribe};
 use crate::messages::unsubscribed::{MESSAGE_TYPE_UNSUBSCRIBED, Unsubscribed};
+use crate::messages::wamp_message::WampMessage;
 use crate::messages::welcome::{MESSAGE_TYPE_WELCOME, Welcome};
 use crate::messages::yield_::{MESSAGE_TYPE_YIELD, Yield};
 
-pub fn to_message(wamp_msg: Vec<Value>) -> Result<Box<dyn Message>, XError> {
+pub fn to_message(wamp_msg: Vec<Value>) -> Result<WampMessage, XError> {
     if wamp_msg.is_empty() {
-        return Err(XError::new("received empty wamp message array"));
+        return Err(XError::protocol_violation("received empty wamp message array"));
     }
 
     match wamp_msg[0] {
-        MESSAGE_TYPE_ABORT => Abort::parse(wamp_msg),
-        MESSAGE_TYPE_AUTHENTICATE => Authenticate::parse(wamp_msg),
-        MESSAGE_TYPE_CALL => Call::parse(wamp_msg),
-        MESSAGE_TYPE_CANCEL => Cancel::parse(wamp_msg),
-        MESSAGE_TYPE_CHALLENGE => Challenge::parse(wamp_msg),
-        MESSAGE_TYPE_ERROR => Error::parse(wamp_msg),
-        MESSAGE_TYPE_EVENT => Event::parse(wamp_msg),
-        MESSAGE_TYPE_GOODBYE => Goodbye::parse(wamp_msg),
-        MESSAGE_TYPE_HELLO => Hello::parse(wamp_msg),
-        MESSAGE_TYPE_INTERRUPT => Interrupt::parse(wamp_msg),
-        MESSAGE_TYPE_INVOCATION => Invocation::parse(wamp_msg),
-        MESSAGE_TYPE_PUBLISH => Publish::parse(wamp_msg),
-        MESSAGE_TYPE_PUBLISHED => Published::parse(wamp_msg),
-        MESSAGE_TYPE_REGISTER => Register::parse(wamp_msg),
-        MESSAGE_TYPE_REGISTERED => Registered::parse(wamp_msg),
-        MESSAGE_TYPE_RESULT => Result_::parse(wamp_msg),
-        MESSAGE_TYPE_SUBSCRIBE => Subscribe::parse(wamp_msg),
-        MESSAGE_TYPE_SUBSCRIBED => Subscribed::parse(wamp_msg),
-        MESSAGE_TYPE_UNSUBSCRIBE => Unsubscribe::parse(wamp_msg),
-        MESSAGE_TYPE_UNSUBSCRIBED => Unsubscribed::parse(wamp_msg),
-        MESSAGE_TYPE_UNREGISTER => Unregister::parse(wamp_msg),
-        MESSAGE_TYPE_UNREGISTERED => Unregistered::parse(wamp_msg),
-        MESSAGE_TYPE_WELCOME => Welcome::parse(wamp_msg),
-        MESSAGE_TYPE_YIELD => Yield::parse(wamp_msg),
-        _ => Err(XError::new(format!(
+        MESSAGE_TYPE_ABORT => Abort::parse(wamp_msg).map(WampMessage::Abort),
+        MESSAGE_TYPE_AUTHENTICATE => Authenticate::parse(wamp_msg).map(WampMessage::Authenticate),
+        MESSAGE_TYPE_CALL => Call::parse(wamp_msg).map(WampMessage::Call),
+        MESSAGE_TYPE_CANCEL => Cancel::parse(wamp_msg).map(WampMessage::Cancel),
+        MESSAGE_TYPE_CHALLENGE => Challenge::parse(wamp_msg).map(WampMessage::Challenge),
+        MESSAGE_TYPE_ERROR => Error::parse(wamp_msg).map(WampMessage::Error),
+        MESSAGE_TYPE_EVENT => Event::parse(wamp_msg).map(WampMessage::Event),
+        MESSAGE_TYPE_GOODBYE => Goodbye::parse(wamp_msg).map(WampMessage::Goodbye),
+        MESSAGE_TYPE_HELLO => Hello::parse(wamp_msg).map(WampMessage::Hello),
+        MESSAGE_TYPE_INTERRUPT => Interrupt::parse(wamp_msg).map(WampMessage::Interrupt),
+        MESSAGE_TYPE_INVOCATION => Invocation::parse(wamp_msg).map(WampMessage::Invocation),
+        MESSAGE_TYPE_PUBLISH => Publish::parse(wamp_msg).map(WampMessage::Publish),
+        MESSAGE_TYPE_PUBLISHED => Published::parse(wamp_msg).map(WampMessage::Published),
+        MESSAGE_TYPE_REGISTER => Register::parse(wamp_msg).map(WampMessage::Register),
+        MESSAGE_TYPE_REGISTERED => Registered::parse(wamp_msg).map(WampMessage::Registered),
+        MESSAGE_TYPE_RESULT => Result_::parse(wamp_msg).map(WampMessage::Result),
+        MESSAGE_TYPE_SUBSCRIBE => Subscribe::parse(wamp_msg).map(WampMessage::Subscribe),
+        MESSAGE_TYPE_SUBSCRIBED => Subscribed::parse(wamp_msg).map(WampMessage::Subscribed),
+        MESSAGE_TYPE_UNSUBSCRIBE => Unsubscribe::parse(wamp_msg).map(WampMessage::Unsubscribe),
+        MESSAGE_TYPE_UNSUBSCRIBED => Unsubscribed::parse(wamp_msg).map(WampMessage::Unsubscribed),
+        MESSAGE_TYPE_UNREGISTER => Unregister::parse(wamp_msg).map(WampMessage::Unregister),
+        MESSAGE_TYPE_UNREGISTERED => Unregistered::parse(wamp_msg).map(WampMessage::Unregistered),
+        MESSAGE_TYPE_WELCOME => Welcome::parse(wamp_msg).map(WampMessage::Welcome),
+        MESSAGE_TYPE_YIELD => Yield::parse(wamp_msg).map(WampMessage::Yield),
+        _ => Err(XError::protocol_violation(format!(
             "received invalid wamp message of type {:?}",
             wamp_msg[0]
         ))),
@@ -1,7 +1,7 @@
 use crate::messages;
 
-use crate::messages::types::Error;
-use crate::serializers::helpers::to_message;
+use crate::messages::types::{Error, Value};
+use crate::messages::wamp_message::WampMessage;
 use crate::serializers::serializer::Serializer;
 use messages::message::Message;
 use serde_json::{from_slice, to_vec};
@@ -12,12 +12,30 @@ pub struct JSONSerializer {}
 impl Serializer for JSONSerializer {
     fn serialize(&self, message: &dyn Message) -> Result<Vec<u8>, Error> {
         to_vec(&message.marshal())
-            .map_err(|_| Error::new(format!("failed to serialize message {:?}", message.message_type())))
+            .map_err(|_| Error::serialization(format!("failed to serialize message {:?}", message.message_type())))
     }
 
-    fn deserialize(&self, payload: Vec<u8>) -> Result<Box<dyn Message>, Error> {
-        let json = from_slice(&payload).map_err(|e| Error::new(format!("failed to deserialize message {e}")))?;
-        to_message(json)
+    fn serialize_message(&self, message: &WampMessage) -> Result<Vec<u8>, Error> {
+        to_vec(message)
+            .map_err(|_| Error::serialization(format!("failed to serialize message {:?}", message.message_type())))
+    }
+
+    fn deserialize(&self, payload: Vec<u8>) -> Result<WampMessage, Error> {
+        self.deserialize_slice(&payload)
+    }
+
+    // typed like the binary serializers: going through a value array first measures the same for JSON (see
+    // benches/serializers.rs) but allocates the array on top
+    fn deserialize_slice(&self, payload: &[u8]) -> Result<WampMessage, Error> {
+        from_slice(payload).map_err(|e| Error::serialization(format!("failed to deserialize message {e}")))
+    }
+
+    fn serialize_value(&self, value: &Value) -> Result<Vec<u8>, Error> {
+        to_vec(value).map_err(|e| Error::serialization(format!("failed to serialize value {e}")))
+    }
+
+    fn deserialize_value(&self, payload: &[u8]) -> Result<Value, Error> {
+        from_slice(payload).map_err(|e| Error::serialization(format!("failed to deserialize value {e}")))
     }
 
     fn is_static(&self) -> bool {
@@ -2,4 +2,5 @@ pub mod cbor;
 pub mod helpers;
 pub mod json;
 pub mod msgpack;
+pub mod registry;
 pub mod serializer;
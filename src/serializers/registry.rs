@@ -0,0 +1,145 @@
+use crate::messages::types::Error;
+use crate::serializers::cbor::CBORSerializer;
+use crate::serializers::json::JSONSerializer;
+use crate::serializers::msgpack::MsgPackSerializer;
+use crate::serializers::serializer::Serializer;
+use crate::transports::rawsocket::SerializerID;
+use crate::transports::websocket::FrameType;
+
+pub const JSON_SUBPROTOCOL: &str = "wamp.2.json";
+pub const MSGPACK_SUBPROTOCOL: &str = "wamp.2.msgpack";
+pub const CBOR_SUBPROTOCOL: &str = "wamp.2.cbor";
+
+#[derive(Debug, Clone)]
+struct Entry {
+    id: SerializerID,
+    subprotocol: String,
+    // WebSocket frames of the serializer's output
+    frame_type: FrameType,
+    serializer: Box<dyn Serializer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SerializerRegistry {
+    // kept in registration order, which is also the order of preference
+    entries: Vec<Entry>,
+}
+
+impl Default for SerializerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SerializerRegistry {
+    pub fn new() -> Self {
+        let mut registry = SerializerRegistry::empty();
+        registry.entries = vec![
+            Entry {
+                id: SerializerID::JSON,
+                subprotocol: JSON_SUBPROTOCOL.to_string(),
+                frame_type: FrameType::Text,
+                serializer: Box::new(JSONSerializer {}),
+            },
+            Entry {
+                id: SerializerID::MSGPACK,
+                subprotocol: MSGPACK_SUBPROTOCOL.to_string(),
+                frame_type: FrameType::Binary,
+                serializer: Box::new(MsgPackSerializer {}),
+            },
+            Entry {
+                id: SerializerID::CBOR,
+                subprotocol: CBOR_SUBPROTOCOL.to_string(),
+                frame_type: FrameType::Binary,
+                serializer: Box::new(CBORSerializer {}),
+            },
+        ];
+
+        registry
+    }
+
+    pub fn empty() -> Self {
+        SerializerRegistry { entries: Vec::new() }
+    }
+
+    pub fn register(
+        &mut self,
+        id: SerializerID,
+        subprotocol: &str,
+        frame_type: FrameType,
+        serializer: Box<dyn Serializer>,
+    ) -> Result<(), Error> {
+        if self.entries.iter().any(|entry| entry.id == id) {
+            return Err(Error::invalid_argument(format!(
+                "serializer ID {} is already registered",
+                id.value()
+            )));
+        }
+
+        if self.entries.iter().any(|entry| entry.subprotocol == subprotocol) {
+            return Err(Error::invalid_argument(format!(
+                "subprotocol '{subprotocol}' is already registered"
+            )));
+        }
+
+        self.entries.push(Entry {
+            id,
+            subprotocol: subprotocol.to_string(),
+            frame_type,
+            serializer,
+        });
+
+        Ok(())
+    }
+
+    pub fn by_id(&self, id: SerializerID) -> Option<Box<dyn Serializer>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.serializer.clone())
+    }
+
+    pub fn by_subprotocol(&self, subprotocol: &str) -> Option<Box<dyn Serializer>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.subprotocol == subprotocol)
+            .map(|entry| entry.serializer.clone())
+    }
+
+    pub fn frame_type(&self, subprotocol: &str) -> Option<FrameType> {
+        self.entries
+            .iter()
+            .find(|entry| entry.subprotocol == subprotocol)
+            .map(|entry| entry.frame_type)
+    }
+
+    pub fn id(&self, subprotocol: &str) -> Option<SerializerID> {
+        self.entries
+            .iter()
+            .find(|entry| entry.subprotocol == subprotocol)
+            .map(|entry| entry.id)
+    }
+
+    // the registered serializer ID for a raw ID, e.g. from a RawSocket handshake
+    pub fn id_from_u8(&self, value: u8) -> Option<SerializerID> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id.value() == value)
+            .map(|entry| entry.id)
+    }
+
+    pub fn subprotocol(&self, id: SerializerID) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.subprotocol.as_str())
+    }
+
+    pub fn ids(&self) -> Vec<SerializerID> {
+        self.entries.iter().map(|entry| entry.id).collect()
+    }
+
+    pub fn subprotocols(&self) -> Vec<&str> {
+        self.entries.iter().map(|entry| entry.subprotocol.as_str()).collect()
+    }
+}
@@ -1,11 +1,35 @@
 use crate::messages::message::Message;
-use crate::messages::types::Error;
+use crate::messages::types::{Error, Value};
+use crate::messages::wamp_message::WampMessage;
 use std::fmt::Debug;
 
 pub trait Serializer: XClone + Debug + Send + Sync {
     fn serialize(&self, message: &dyn Message) -> Result<Vec<u8>, Error>;
-    fn deserialize(&self, payload: Vec<u8>) -> Result<Box<dyn Message>, Error>;
+    fn deserialize(&self, payload: Vec<u8>) -> Result<WampMessage, Error>;
     fn is_static(&self) -> bool;
+
+    // encodes a standalone value, as needed for passthru payloads
+    fn serialize_value(&self, _value: &Value) -> Result<Vec<u8>, Error> {
+        Err(Error::serialization(
+            "serializer does not support encoding standalone values",
+        ))
+    }
+
+    fn deserialize_value(&self, _payload: &[u8]) -> Result<Value, Error> {
+        Err(Error::serialization(
+            "serializer does not support decoding standalone values",
+        ))
+    }
+
+    // serializers able to encode messages through serde directly override this to skip marshal
+    fn serialize_message(&self, message: &WampMessage) -> Result<Vec<u8>, Error> {
+        self.serialize(message)
+    }
+
+    // serializers that can decode from a borrowed buffer override this to skip the copy
+    fn deserialize_slice(&self, payload: &[u8]) -> Result<WampMessage, Error> {
+        self.deserialize(payload.to_vec())
+    }
 }
 
 pub trait XClone {
@@ -0,0 +1,605 @@
+use crate::clock::{Clock, DeadlineTracker};
+use crate::messages::call::MESSAGE_TYPE_CALL;
+use crate::messages::cancel::Cancel;
+use crate::messages::invocation::MESSAGE_TYPE_INVOCATION;
+use crate::messages::message::Message;
+use crate::messages::options::{CallOptions, CancelMode, CancelOptions, PublishOptions, YieldOptions};
+use crate::messages::publish::MESSAGE_TYPE_PUBLISH;
+use crate::messages::register::MESSAGE_TYPE_REGISTER;
+use crate::messages::subscribe::MESSAGE_TYPE_SUBSCRIBE;
+use crate::messages::types::Error;
+use crate::messages::unregister::MESSAGE_TYPE_UNREGISTER;
+use crate::messages::unsubscribe::MESSAGE_TYPE_UNSUBSCRIBE;
+use crate::messages::wamp_message::WampMessage;
+use crate::serializers::serializer::Serializer;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct Session {
+    serializer: Box<dyn Serializer>,
+
+    // data structures for RPC, calls and invocations map to whether progressive results were requested
+    call_requests: HashMap<i64, bool>,
+    progressive_calls: HashSet<i64>,
+    call_timeouts: DeadlineTracker<i64>,
+    register_requests: HashSet<i64>,
+    registrations: HashSet<i64>,
+    invocation_requests: HashMap<i64, bool>,
+    progressive_invocations: HashSet<i64>,
+    unregister_requests: HashMap<i64, i64>,
+
+    // data structures for PubSub
+    publish_requests: HashSet<i64>,
+    subscribe_requests: HashSet<i64>,
+    subscriptions: HashSet<i64>,
+    unsubscribe_requests: HashMap<i64, i64>,
+
+    // set once a GOODBYE or ABORT was received, only the GOODBYE reply may still be sent
+    closed: bool,
+}
+
+impl Session {
+    pub fn new(serializer: Box<dyn Serializer>) -> Self {
+        Session {
+            serializer,
+            call_requests: HashMap::new(),
+            progressive_calls: HashSet::new(),
+            call_timeouts: DeadlineTracker::default(),
+            register_requests: HashSet::new(),
+            registrations: HashSet::new(),
+            invocation_requests: HashMap::new(),
+            progressive_invocations: HashSet::new(),
+            unregister_requests: HashMap::new(),
+            publish_requests: HashSet::new(),
+            subscribe_requests: HashSet::new(),
+            subscriptions: HashSet::new(),
+            unsubscribe_requests: HashMap::new(),
+            closed: false,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.call_timeouts = DeadlineTracker::new(clock);
+        self
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    // time left until the next call times out, for scheduling the next expire_calls
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.call_timeouts.next_timeout()
+    }
+
+    // CANCELs for the calls that ran past their timeout, to be passed to send_message
+    pub fn expire_calls(&mut self) -> Vec<WampMessage> {
+        self.call_timeouts
+            .expired()
+            .into_iter()
+            .filter(|request_id| self.call_requests.contains_key(request_id))
+            .map(|request_id| {
+                Cancel {
+                    request_id,
+                    options: CancelOptions::new().with_mode(CancelMode::KillNoWait).marshal(),
+                }
+                .into()
+            })
+            .collect()
+    }
+
+    pub fn send_message(&mut self, msg: &WampMessage) -> Result<Vec<u8>, Error> {
+        self.validate_send(msg)?;
+        // the request is only tracked once it is certain to go out
+        let data = self.serializer.serialize_message(msg)?;
+        self.track_send(msg);
+
+        Ok(data)
+    }
+
+    fn validate_send(&self, msg: &WampMessage) -> Result<(), Error> {
+        if self.closed && !matches!(msg, WampMessage::Goodbye(_)) {
+            return Err(Error::invalid_state("cannot send on a closed session"));
+        }
+
+        match msg {
+            WampMessage::Call(call) => {
+                CallOptions::parse(&call.options)?;
+                // only a progressive call invocation may reuse its request_id
+                if self.call_requests.contains_key(&call.request_id)
+                    && !self.progressive_calls.contains(&call.request_id)
+                {
+                    return Err(Error::invalid_state("cannot call with request_id of a pending call"));
+                }
+            }
+
+            WampMessage::Cancel(cancel) => {
+                CancelOptions::parse(&cancel.options)?;
+                if !self.call_requests.contains_key(&cancel.request_id) {
+                    return Err(Error::invalid_state("cannot cancel unknown call request"));
+                }
+            }
+
+            WampMessage::Yield(yield_) => {
+                let options = YieldOptions::parse(&yield_.options)?;
+                let receive_progress = match self.invocation_requests.get(&yield_.request_id) {
+                    Some(receive_progress) => *receive_progress,
+                    None => return Err(Error::invalid_state("cannot yield for unknown invocation request")),
+                };
+
+                if options.progress == Some(true) && !receive_progress {
+                    return Err(Error::invalid_state(
+                        "cannot send progressive yield, caller did not ask for progressive results",
+                    ));
+                }
+            }
+
+            WampMessage::Register(register) => {
+                if self.register_requests.contains(&register.request_id) {
+                    return Err(Error::invalid_state(
+                        "cannot register with request_id of a pending register",
+                    ));
+                }
+            }
+
+            WampMessage::Unregister(unregister) => {
+                if !self.registrations.contains(&unregister.registration_id) {
+                    return Err(Error::invalid_state("cannot unregister unknown registration"));
+                }
+            }
+
+            WampMessage::Publish(publish) => {
+                PublishOptions::parse(&publish.options)?;
+            }
+
+            WampMessage::Subscribe(subscribe) => {
+                if self.subscribe_requests.contains(&subscribe.request_id) {
+                    return Err(Error::invalid_state(
+                        "cannot subscribe with request_id of a pending subscribe",
+                    ));
+                }
+            }
+
+            WampMessage::Unsubscribe(unsubscribe) => {
+                if !self.subscriptions.contains(&unsubscribe.subscription_id) {
+                    return Err(Error::invalid_state("cannot unsubscribe unknown subscription"));
+                }
+            }
+
+            WampMessage::Error(error) => {
+                if error.message_type != MESSAGE_TYPE_INVOCATION {
+                    return Err(Error::invalid_state("send only supported for invocation error"));
+                }
+
+                if !self.invocation_requests.contains_key(&error.request_id) {
+                    return Err(Error::invalid_state("cannot send error for unknown invocation request"));
+                }
+            }
+
+            WampMessage::Goodbye(_) => {}
+
+            _ => {
+                return Err(Error::invalid_state(format!(
+                    "send not supported for message of type {:?}",
+                    msg.message_type()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // only called after validate_send accepted the message, so the options are known to parse
+    fn track_send(&mut self, msg: &WampMessage) {
+        match msg {
+            WampMessage::Call(call) => {
+                let options = CallOptions::parse(&call.options).unwrap_or_default();
+                if let Entry::Vacant(entry) = self.call_requests.entry(call.request_id) {
+                    entry.insert(options.receive_progress == Some(true));
+                    if let Some(timeout) = options.timeout_duration() {
+                        self.call_timeouts.start(call.request_id, timeout);
+                    }
+                }
+
+                if options.progress == Some(true) {
+                    self.progressive_calls.insert(call.request_id);
+                } else {
+                    self.progressive_calls.remove(&call.request_id);
+                }
+            }
+
+            WampMessage::Yield(yield_) => {
+                let options = YieldOptions::parse(&yield_.options).unwrap_or_default();
+                if options.progress != Some(true) {
+                    self.invocation_requests.remove(&yield_.request_id);
+                    self.progressive_invocations.remove(&yield_.request_id);
+                }
+            }
+
+            WampMessage::Register(register) => {
+                self.register_requests.insert(register.request_id);
+            }
+
+            WampMessage::Unregister(unregister) => {
+                self.unregister_requests
+                    .insert(unregister.request_id, unregister.registration_id);
+            }
+
+            WampMessage::Publish(publish) => {
+                let options = PublishOptions::parse(&publish.options).unwrap_or_default();
+                if options.acknowledge == Some(true) {
+                    self.publish_requests.insert(publish.request_id);
+                }
+            }
+
+            WampMessage::Subscribe(subscribe) => {
+                self.subscribe_requests.insert(subscribe.request_id);
+            }
+
+            WampMessage::Unsubscribe(unsubscribe) => {
+                self.unsubscribe_requests
+                    .insert(unsubscribe.request_id, unsubscribe.subscription_id);
+            }
+
+            WampMessage::Error(error) => {
+                self.invocation_requests.remove(&error.request_id);
+                self.progressive_invocations.remove(&error.request_id);
+            }
+
+            _ => {}
+        }
+    }
+
+    pub fn receive(&mut self, data: Vec<u8>) -> Result<WampMessage, Error> {
+        match self.serializer.deserialize(data) {
+            Ok(msg) => self.receive_message(msg),
+            Err(e) => Err(Error::serialization(format!(
+                "failed to deserialize message: {}",
+                e.message()
+            ))),
+        }
+    }
+
+    pub fn receive_message(&mut self, msg: WampMessage) -> Result<WampMessage, Error> {
+        if self.closed {
+            return Err(Error::invalid_state("cannot receive on a closed session"));
+        }
+
+        match &msg {
+            WampMessage::Result(result) => {
+                let receive_progress = match self.call_requests.get(&result.request_id) {
+                    Some(receive_progress) => *receive_progress,
+                    None => return Err(Error::protocol_violation("received RESULT for invalid request_id")),
+                };
+
+                if result.progress() {
+                    if !receive_progress {
+                        return Err(Error::protocol_violation(
+                            "received progressive RESULT for a call that didn't ask for progressive results",
+                        ));
+                    }
+                } else {
+                    // a final result ends the call
+                    self.call_requests.remove(&result.request_id);
+                    self.progressive_calls.remove(&result.request_id);
+                    self.call_timeouts.stop(&result.request_id);
+                }
+            }
+
+            WampMessage::Registered(registered) => {
+                if !self.register_requests.remove(&registered.request_id) {
+                    return Err(Error::protocol_violation("received REGISTERED for invalid request_id"));
+                }
+
+                self.registrations.insert(registered.registration_id);
+            }
+
+            WampMessage::Unregistered(unregistered) => {
+                match self.unregister_requests.remove(&unregistered.request_id) {
+                    Some(registration_id) => {
+                        self.registrations.remove(&registration_id);
+                    }
+                    None => {
+                        return Err(Error::protocol_violation(
+                            "received UNREGISTERED for invalid request_id",
+                        ));
+                    }
+                }
+            }
+
+            WampMessage::Invocation(invocation) => {
+                if !self.registrations.contains(&invocation.registration_id) {
+                    return Err(Error::protocol_violation(
+                        "received INVOCATION for invalid registration_id",
+                    ));
+                }
+
+                // follow-up INVOCATIONs are only expected while the caller is still sending progressively
+                if self.invocation_requests.contains_key(&invocation.request_id)
+                    && !self.progressive_invocations.contains(&invocation.request_id)
+                {
+                    return Err(Error::protocol_violation(
+                        "received INVOCATION for request_id of a pending invocation",
+                    ));
+                }
+
+                self.invocation_requests
+                    .entry(invocation.request_id)
+                    .or_insert(invocation.receive_progress());
+
+                if invocation.progress() {
+                    self.progressive_invocations.insert(invocation.request_id);
+                } else {
+                    self.progressive_invocations.remove(&invocation.request_id);
+                }
+            }
+
+            // the invocation stays pending, the dealer still expects an ERROR (or YIELD) in reply. An INTERRUPT may
+            // also cross paths with our final YIELD, so it isn't checked against the pending invocations
+            WampMessage::Interrupt(_) => {}
+
+            WampMessage::Published(published) => {
+                if !self.publish_requests.remove(&published.request_id) {
+                    return Err(Error::protocol_violation("received PUBLISHED for invalid request_id"));
+                }
+            }
+
+            WampMessage::Subscribed(subscribed) => {
+                if !self.subscribe_requests.remove(&subscribed.request_id) {
+                    return Err(Error::protocol_violation("received SUBSCRIBED for invalid request_id"));
+                }
+
+                self.subscriptions.insert(subscribed.subscription_id);
+            }
+
+            WampMessage::Unsubscribed(unsubscribed) => {
+                match self.unsubscribe_requests.remove(&unsubscribed.request_id) {
+                    Some(subscription_id) => {
+                        self.subscriptions.remove(&subscription_id);
+                    }
+                    None => {
+                        return Err(Error::protocol_violation(
+                            "received UNSUBSCRIBED for invalid request_id",
+                        ));
+                    }
+                }
+            }
+
+            WampMessage::Event(event) => {
+                if !self.subscriptions.contains(&event.subscription_id) {
+                    return Err(Error::protocol_violation("received EVENT for invalid subscription_id"));
+                }
+            }
+
+            WampMessage::Error(error) => {
+                let removed = match error.message_type {
+                    MESSAGE_TYPE_CALL => {
+                        self.progressive_calls.remove(&error.request_id);
+                        self.call_timeouts.stop(&error.request_id);
+                        self.call_requests.remove(&error.request_id).is_some()
+                    }
+                    MESSAGE_TYPE_REGISTER => self.register_requests.remove(&error.request_id),
+                    MESSAGE_TYPE_UNREGISTER => self.unregister_requests.remove(&error.request_id).is_some(),
+                    MESSAGE_TYPE_SUBSCRIBE => self.subscribe_requests.remove(&error.request_id),
+                    MESSAGE_TYPE_UNSUBSCRIBE => self.unsubscribe_requests.remove(&error.request_id).is_some(),
+                    MESSAGE_TYPE_PUBLISH => self.publish_requests.remove(&error.request_id),
+                    _ => {
+                        return Err(Error::protocol_violation(format!(
+                            "received ERROR for invalid message type {:?}",
+                            error.message_type
+                        )));
+                    }
+                };
+
+                if !removed {
+                    return Err(Error::protocol_violation("received ERROR for invalid request_id"));
+                }
+            }
+
+            // the router ended the session, nothing that is still pending will get a reply
+            WampMessage::Goodbye(_) | WampMessage::Abort(_) => self.close(),
+
+            _ => {
+                return Err(Error::protocol_violation(format!(
+                    "received unexpected message of type {:?}",
+                    msg.message_type()
+                )));
+            }
+        }
+
+        Ok(msg)
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.call_requests.clear();
+        self.progressive_calls.clear();
+        self.call_timeouts = DeadlineTracker::new(self.call_timeouts.clock());
+        self.register_requests.clear();
+        self.registrations.clear();
+        self.invocation_requests.clear();
+        self.progressive_invocations.clear();
+        self.unregister_requests.clear();
+        self.publish_requests.clear();
+        self.subscribe_requests.clear();
+        self.subscriptions.clear();
+        self.unsubscribe_requests.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::messages::abort::Abort;
+    use crate::messages::call::Call;
+    use crate::messages::error::Error as ErrorMessage;
+    use crate::messages::event::Event;
+    use crate::messages::goodbye::Goodbye;
+    use crate::messages::result::Result_;
+    use crate::messages::types::{ERROR_PROTOCOL_VIOLATION, Value};
+    use crate::messages::yield_::Yield;
+    use crate::serializers::json::JSONSerializer;
+
+    fn session() -> Session {
+        Session::new(Box::new(JSONSerializer {}))
+    }
+
+    fn call(request_id: i64, options: CallOptions) -> WampMessage {
+        Call {
+            request_id,
+            options: options.marshal(),
+            procedure: "foo.bar".to_string(),
+            args: None,
+            kwargs: None,
+        }
+        .into()
+    }
+
+    fn result(request_id: i64, progress: bool) -> WampMessage {
+        let mut details = HashMap::new();
+        if progress {
+            details.insert("progress".to_string(), Value::Bool(true));
+        }
+
+        Result_ {
+            request_id,
+            details,
+            args: None,
+            kwargs: None,
+        }
+        .into()
+    }
+
+    fn goodbye() -> WampMessage {
+        Goodbye {
+            details: HashMap::new(),
+            reason: "wamp.close.close_realm".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn call_result() {
+        let mut session = session();
+        session.send_message(&call(1, CallOptions::new())).unwrap();
+        assert!(session.send_message(&call(1, CallOptions::new())).is_err());
+
+        session.receive_message(result(1, false)).unwrap();
+        let err = session.receive_message(result(1, false)).unwrap_err();
+        assert_eq!(err.uri(), ERROR_PROTOCOL_VIOLATION);
+    }
+
+    #[test]
+    fn progressive_results() {
+        let mut session = session();
+        session.send_message(&call(1, CallOptions::new())).unwrap();
+        assert!(session.receive_message(result(1, true)).is_err());
+
+        session
+            .send_message(&call(2, CallOptions::new().with_receive_progress(true)))
+            .unwrap();
+        session.receive_message(result(2, true)).unwrap();
+        session.receive_message(result(2, true)).unwrap();
+        session.receive_message(result(2, false)).unwrap();
+        assert!(session.receive_message(result(2, true)).is_err());
+    }
+
+    #[test]
+    fn call_error() {
+        let mut session = session();
+        session.send_message(&call(1, CallOptions::new())).unwrap();
+
+        let error = |request_id| {
+            WampMessage::from(ErrorMessage {
+                message_type: MESSAGE_TYPE_CALL,
+                request_id,
+                options: HashMap::new(),
+                uri: "wamp.error.no_such_procedure".to_string(),
+                args: None,
+                kwargs: None,
+            })
+        };
+
+        session.receive_message(error(1)).unwrap();
+        assert!(session.receive_message(error(1)).is_err());
+    }
+
+    #[test]
+    fn unknown_requests_are_rejected() {
+        let mut session = session();
+        let yield_ = Yield {
+            request_id: 1,
+            options: HashMap::new(),
+            args: None,
+            kwargs: None,
+        };
+        assert!(session.send_message(&yield_.into()).is_err());
+
+        let event = Event {
+            subscription_id: 1,
+            publication_id: 1,
+            details: HashMap::new(),
+            args: None,
+            kwargs: None,
+        };
+        let err = session.receive_message(event.into()).unwrap_err();
+        assert_eq!(err.uri(), ERROR_PROTOCOL_VIOLATION);
+    }
+
+    #[test]
+    fn call_timeout() {
+        let clock = Arc::new(ManualClock::new());
+        let mut session = session().with_clock(clock.clone());
+        session
+            .send_message(&call(1, CallOptions::new().with_timeout(1000)))
+            .unwrap();
+        assert_eq!(session.next_timeout(), Some(Duration::from_secs(1)));
+        assert!(session.expire_calls().is_empty());
+
+        clock.advance(Duration::from_secs(1));
+        let expired = session.expire_calls();
+        let expected = Cancel {
+            request_id: 1,
+            options: CancelOptions::new().with_mode(CancelMode::KillNoWait).marshal(),
+        };
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].marshal(), expected.marshal());
+    }
+
+    #[test]
+    fn goodbye_closes() {
+        let mut session = session();
+        session.send_message(&call(1, CallOptions::new())).unwrap();
+
+        session.receive_message(goodbye()).unwrap();
+        assert!(session.is_closed());
+        assert!(session.send_message(&call(2, CallOptions::new())).is_err());
+        assert!(session.receive_message(result(1, false)).is_err());
+
+        // the GOODBYE reply still goes out
+        session.send_message(&goodbye()).unwrap();
+    }
+
+    #[test]
+    fn abort_closes() {
+        let mut session = session();
+        session.send_message(&call(1, CallOptions::new())).unwrap();
+
+        let abort = Abort {
+            details: HashMap::new(),
+            reason: "wamp.error.system_shutdown".to_string(),
+            args: None,
+            kwargs: None,
+        };
+        match session.receive_message(abort.into()).unwrap() {
+            WampMessage::Abort(abort) => assert_eq!(abort.reason, "wamp.error.system_shutdown"),
+            other => panic!("expected ABORT, got {other:?}"),
+        }
+
+        assert!(session.is_closed());
+        assert!(session.receive_message(result(1, false)).is_err());
+    }
+}
@@ -1 +1,2 @@
 pub mod rawsocket;
+pub mod websocket;
@@ -1,25 +1,43 @@
 use crate::messages::types::Error;
+use crate::serializers::registry::SerializerRegistry;
 
 const MAGIC: u8 = 0x7F;
 pub const PROTOCOL_MAX_MSG_SIZE: usize = 1 << 24;
 pub const DEFAULT_MAX_MSG_SIZE: usize = 1 << 20;
 
-#[derive(Debug, Clone, Copy)]
-pub enum SerializerID {
-    JSON = 1,
-    MSGPACK = 2,
-    CBOR = 3,
-}
+// serializer IDs occupy the lower nibble of the handshake, so IDs 4..=15 are free for custom serializers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SerializerID(u8);
 
 impl SerializerID {
+    pub const JSON: SerializerID = SerializerID(1);
+    pub const MSGPACK: SerializerID = SerializerID(2);
+    pub const CBOR: SerializerID = SerializerID(3);
+
+    // only the serializers defined by the spec, IDs of custom serializers are resolved by a SerializerRegistry
     pub fn from_u8(value: u8) -> Option<SerializerID> {
         match value {
-            1 => Some(SerializerID::JSON),
-            2 => Some(SerializerID::MSGPACK),
-            3 => Some(SerializerID::CBOR),
+            1..=3 => Some(SerializerID(value)),
             _ => None,
         }
     }
+
+    pub fn custom(value: u8) -> Option<SerializerID> {
+        match value {
+            4..=15 => Some(SerializerID(value)),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl From<SerializerID> for u8 {
+    fn from(id: SerializerID) -> u8 {
+        id.value()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -65,47 +83,172 @@ impl Handshake {
 
 pub fn send_handshake(hs: &Handshake) -> Result<Vec<u8>, Error> {
     if hs.max_message_size() > PROTOCOL_MAX_MSG_SIZE {
-        return Err(Error::new("max_message_size must not be more than 16 megabytes"));
+        return Err(Error::transport("max_message_size must not be more than 16 megabytes"));
     }
 
     let log2 = (hs.max_message_size() as f64).log2() as u8;
     if (1 << log2) != hs.max_message_size() || log2 < 9 {
-        return Err(Error::new("max_message_size must be a power of 2 and >= 512"));
+        return Err(Error::transport("max_message_size must be a power of 2 and >= 512"));
     }
 
-    let b1 = ((log2 - 9) << 4) | ((hs.serializer_id() as u8) & 0x0F);
+    let b1 = ((log2 - 9) << 4) | (hs.serializer_id().value() & 0x0F);
     Ok(vec![MAGIC, b1, 0x00, 0x00])
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeError {
+    SerializerUnsupported = 1,
+    MaxLengthUnacceptable = 2,
+    ReservedBitsUsed = 3,
+    MaxConnectionCountReached = 4,
+}
+
+impl HandshakeError {
+    pub fn from_u8(value: u8) -> Option<HandshakeError> {
+        match value {
+            1 => Some(HandshakeError::SerializerUnsupported),
+            2 => Some(HandshakeError::MaxLengthUnacceptable),
+            3 => Some(HandshakeError::ReservedBitsUsed),
+            4 => Some(HandshakeError::MaxConnectionCountReached),
+            _ => None,
+        }
+    }
+}
+
+// the handshake of the other side, or the reason it was refused
+#[derive(Debug)]
+pub enum HandshakeOutcome {
+    Accepted(Handshake),
+    Rejected(HandshakeError),
+}
+
+pub fn send_handshake_error(error: HandshakeError) -> Vec<u8> {
+    vec![MAGIC, (error as u8) << 4, 0x00, 0x00]
+}
+
 pub fn receive_handshake(data: &[u8]) -> Result<Handshake, Error> {
+    receive_handshake_with(data, &SerializerRegistry::default())
+}
+
+// for routers that may pick one of the custom serializers in the registry
+pub fn receive_handshake_with(data: &[u8], registry: &SerializerRegistry) -> Result<Handshake, Error> {
+    match receive_handshake_outcome_with(data, registry)? {
+        HandshakeOutcome::Accepted(hs) => Ok(hs),
+        HandshakeOutcome::Rejected(error) => Err(Error::transport(format!("handshake rejected by router: {error:?}"))),
+    }
+}
+
+pub fn receive_handshake_outcome(data: &[u8]) -> Result<HandshakeOutcome, Error> {
+    receive_handshake_outcome_with(data, &SerializerRegistry::default())
+}
+
+pub fn receive_handshake_outcome_with(data: &[u8], registry: &SerializerRegistry) -> Result<HandshakeOutcome, Error> {
     if data.len() != 4 {
-        return Err(Error::new(format!(
+        return Err(Error::transport(format!(
             "expected 4 bytes for handshake response, got {}",
             data.len()
         )));
     }
 
     if data[0] != MAGIC {
-        return Err(Error::new(format!("expected MAGIC, got {}", data[0])));
+        return Err(Error::transport(format!("expected MAGIC, got {}", data[0])));
     }
 
     if data[2] != 0x00 || data[3] != 0x00 {
-        return Err(Error::new(format!(
+        return Err(Error::transport(format!(
             "expected 0x00 for third and fourth byte, got {} and {}",
             data[2], data[3]
         )));
     }
 
-    if let Some(serializer) = SerializerID::from_u8(data[1] & 0x0F) {
+    // a zero serializer nibble marks an error reply, the error code is in the upper nibble
+    if data[1] & 0x0F == 0 {
+        return match HandshakeError::from_u8(data[1] >> 4) {
+            Some(error) => Ok(HandshakeOutcome::Rejected(error)),
+            None => Err(Error::transport(format!(
+                "got invalid handshake error code {}",
+                data[1] >> 4
+            ))),
+        };
+    }
+
+    if let Some(serializer) = registry.id_from_u8(data[1] & 0x0F) {
         let size_shift = (data[1] >> 4) + 9;
         let max_message_size = 1 << size_shift;
 
-        Ok(Handshake::new(serializer, max_message_size))
+        Ok(HandshakeOutcome::Accepted(Handshake::new(serializer, max_message_size)))
     } else {
-        Err(Error::new("got invalid serializer byte"))
+        Err(Error::transport("got invalid serializer byte"))
+    }
+}
+
+#[derive(Debug)]
+pub struct HandshakeAcceptor {
+    registry: SerializerRegistry,
+    max_message_size: usize,
+    min_message_size: usize,
+}
+
+impl HandshakeAcceptor {
+    // only the serializers of the registry are agreed to
+    pub fn new(registry: SerializerRegistry, max_message_size: usize) -> Self {
+        HandshakeAcceptor {
+            registry,
+            max_message_size,
+            min_message_size: 1 << 9,
+        }
+    }
+
+    // clients announcing a smaller max_message_size are refused
+    pub fn with_min_message_size(mut self, min_message_size: usize) -> Self {
+        self.min_message_size = min_message_size;
+        self
+    }
+
+    // to look up the serializer of an accepted handshake
+    pub fn registry(&self) -> &SerializerRegistry {
+        &self.registry
+    }
+
+    // returns the client's handshake (or why it was refused) along with the reply to send back
+    pub fn receive_handshake(&self, data: &[u8]) -> Result<(HandshakeOutcome, Vec<u8>), Error> {
+        if data.len() != 4 {
+            return Err(Error::transport(format!(
+                "expected 4 bytes for handshake request, got {}",
+                data.len()
+            )));
+        }
+
+        if data[0] != MAGIC {
+            return Err(Error::transport(format!("expected MAGIC, got {}", data[0])));
+        }
+
+        if data[2] != 0x00 || data[3] != 0x00 {
+            return Ok(reject(HandshakeError::ReservedBitsUsed));
+        }
+
+        let serializer = match self.registry.id_from_u8(data[1] & 0x0F) {
+            Some(serializer) => serializer,
+            None => return Ok(reject(HandshakeError::SerializerUnsupported)),
+        };
+
+        let max_message_size = 1 << ((data[1] >> 4) + 9);
+        if max_message_size < self.min_message_size {
+            return Ok(reject(HandshakeError::MaxLengthUnacceptable));
+        }
+
+        let reply = send_handshake(&Handshake::new(serializer, self.max_message_size))?;
+        Ok((
+            HandshakeOutcome::Accepted(Handshake::new(serializer, max_message_size)),
+            reply,
+        ))
     }
 }
 
+fn reject(error: HandshakeError) -> (HandshakeOutcome, Vec<u8>) {
+    (HandshakeOutcome::Rejected(error), send_handshake_error(error))
+}
+
 #[derive(Debug)]
 pub struct MessageHeader {
     kind: Message,
@@ -133,14 +276,14 @@ pub fn send_message_header(header: &MessageHeader) -> Vec<u8> {
 
 pub fn receive_message_header(data: &[u8]) -> Result<MessageHeader, Error> {
     if data.len() != 4 {
-        return Err(Error::new("expected 4 bytes for message header"));
+        return Err(Error::transport("expected 4 bytes for message header"));
     }
 
     if let Some(kind) = Message::from_u8(data[0]) {
         let length = bytes_to_int(&data[1..4]);
         Ok(MessageHeader::new(kind, length))
     } else {
-        Err(Error::new("received invalid message type"))
+        Err(Error::transport("received invalid message type"))
     }
 }
 
@@ -151,3 +294,123 @@ fn int_to_bytes(i: usize) -> [u8; 3] {
 fn bytes_to_int(b: &[u8]) -> usize {
     b.iter().fold(0, |acc, &byte| (acc << 8) | byte as usize)
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawSocketEvent {
+    // a complete WAMP message, ready to be handed to the serializer
+    Message(Vec<u8>),
+    // a frame the peer produced on its own (e.g. PONG for a received PING) that must be written to the socket
+    Reply(Vec<u8>),
+    // payload of a PONG answering one of our PINGs
+    Pong(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct RawSocketPeer {
+    max_message_size: usize,
+    peer_max_message_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl RawSocketPeer {
+    pub fn new(max_message_size: usize) -> Self {
+        RawSocketPeer {
+            max_message_size,
+            peer_max_message_size: max_message_size,
+            buffer: Vec::new(),
+        }
+    }
+
+    // limit announced by the other side, applies to frames we send
+    pub fn with_peer_max_message_size(mut self, peer_max_message_size: usize) -> Self {
+        self.peer_max_message_size = peer_max_message_size;
+        self
+    }
+
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    pub fn peer_max_message_size(&self) -> usize {
+        self.peer_max_message_size
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn send_message(&self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        self.frame(Message::Wamp, payload)
+    }
+
+    pub fn ping(&self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        self.frame(Message::Ping, payload)
+    }
+
+    // a failing frame doesn't lose the ones decoded before it: those are returned first and the error is
+    // reported by the next call to receive
+    pub fn receive(&mut self, data: &[u8]) -> Result<Vec<RawSocketEvent>, Error> {
+        self.buffer.extend_from_slice(data);
+
+        let mut events = Vec::new();
+        let mut consumed = 0;
+        loop {
+            match self.next_event(&self.buffer[consumed..]) {
+                Ok(Some((event, frame_length))) => {
+                    events.push(event);
+                    consumed += frame_length;
+                }
+                Ok(None) => break,
+                Err(_) if !events.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.buffer.drain(..consumed);
+        Ok(events)
+    }
+
+    // the event of the first complete frame in the buffer along with the frame's length
+    fn next_event(&self, buffer: &[u8]) -> Result<Option<(RawSocketEvent, usize)>, Error> {
+        if buffer.len() < 4 {
+            return Ok(None);
+        }
+
+        let header = receive_message_header(&buffer[..4])?;
+        if header.length() > self.max_message_size {
+            return Err(Error::transport(format!(
+                "received frame of {} bytes, exceeds max_message_size of {}",
+                header.length(),
+                self.max_message_size
+            )));
+        }
+
+        let frame_length = 4 + header.length();
+        if buffer.len() < frame_length {
+            return Ok(None);
+        }
+
+        let payload = buffer[4..frame_length].to_vec();
+        let event = match header.kind() {
+            Message::Wamp => RawSocketEvent::Message(payload),
+            Message::Ping => RawSocketEvent::Reply(self.frame(Message::Pong, &payload)?),
+            Message::Pong => RawSocketEvent::Pong(payload),
+        };
+
+        Ok(Some((event, frame_length)))
+    }
+
+    fn frame(&self, kind: Message, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        if payload.len() > self.peer_max_message_size {
+            return Err(Error::transport(format!(
+                "cannot send frame of {} bytes, exceeds max_message_size of {}",
+                payload.len(),
+                self.peer_max_message_size
+            )));
+        }
+
+        let mut frame = send_message_header(&MessageHeader::new(kind, payload.len()));
+        frame.extend_from_slice(payload);
+        Ok(frame)
+    }
+}
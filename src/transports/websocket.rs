@@ -0,0 +1,82 @@
+use crate::messages::types::Error;
+use crate::serializers::registry::SerializerRegistry;
+use crate::serializers::serializer::Serializer;
+
+pub const HEADER_SEC_WEBSOCKET_PROTOCOL: &str = "Sec-WebSocket-Protocol";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    Text,
+    Binary,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebSocketProtocol {
+    subprotocol: String,
+    serializer: Box<dyn Serializer>,
+    frame_type: FrameType,
+}
+
+impl WebSocketProtocol {
+    pub fn new(subprotocol: &str, serializer: Box<dyn Serializer>, frame_type: FrameType) -> Self {
+        WebSocketProtocol {
+            subprotocol: subprotocol.to_string(),
+            serializer,
+            frame_type,
+        }
+    }
+
+    pub fn subprotocol(&self) -> &str {
+        &self.subprotocol
+    }
+
+    pub fn serializer(&self) -> Box<dyn Serializer> {
+        self.serializer.clone()
+    }
+
+    pub fn frame_type(&self) -> FrameType {
+        self.frame_type
+    }
+}
+
+// splits a Sec-WebSocket-Protocol header value into its subprotocols
+pub fn parse_subprotocols(header: &str) -> Vec<&str> {
+    header
+        .split(',')
+        .map(str::trim)
+        .filter(|subprotocol| !subprotocol.is_empty())
+        .collect()
+}
+
+// the Sec-WebSocket-Protocol value a client sends, listing the registered serializers in order of preference
+pub fn offer_subprotocols(registry: &SerializerRegistry) -> String {
+    registry.subprotocols().join(", ")
+}
+
+// server side: picks the registered serializer that comes first in the registry's order of preference among those
+// offered by the client
+pub fn negotiate_subprotocol(offers: &[&str], registry: &SerializerRegistry) -> Result<WebSocketProtocol, Error> {
+    registry
+        .subprotocols()
+        .into_iter()
+        .find(|subprotocol| offers.contains(subprotocol))
+        .and_then(|subprotocol| protocol(subprotocol, registry))
+        .ok_or_else(|| Error::transport(format!("none of the offered subprotocols {offers:?} are supported")))
+}
+
+// client side: validates the subprotocol selected by the server
+pub fn accept_subprotocol(selected: Option<&str>, registry: &SerializerRegistry) -> Result<WebSocketProtocol, Error> {
+    let selected = match selected {
+        Some(selected) => selected.trim(),
+        None => return Err(Error::transport("server did not select a subprotocol")),
+    };
+
+    protocol(selected, registry)
+        .ok_or_else(|| Error::transport(format!("server selected unsupported subprotocol '{selected}'")))
+}
+
+fn protocol(subprotocol: &str, registry: &SerializerRegistry) -> Option<WebSocketProtocol> {
+    let serializer = registry.by_subprotocol(subprotocol)?;
+    let frame_type = registry.frame_type(subprotocol)?;
+    Some(WebSocketProtocol::new(subprotocol, serializer, frame_type))
+}
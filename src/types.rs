@@ -1,9 +1,14 @@
+use crate::messages::wamp_message::WampMessage;
+use crate::roles::Roles;
+
+#[derive(Debug, Clone)]
 pub struct SessionDetails {
     pub id: i64,
     pub realm: String,
     pub authid: String,
     pub auth_role: String,
     pub static_serializer: bool,
+    pub roles: Roles,
 }
 
 impl SessionDetails {
@@ -14,6 +19,26 @@ impl SessionDetails {
             authid,
             auth_role,
             static_serializer,
+            roles: Roles::new(),
+        }
+    }
+
+    pub fn with_roles(mut self, roles: Roles) -> Self {
+        self.roles = roles;
+        self
+    }
+}
+
+pub struct MessageWithRecipient {
+    pub message: WampMessage,
+    pub recipient: i64,
+}
+
+impl MessageWithRecipient {
+    pub fn new<M: Into<WampMessage>>(message: M, recipient: i64) -> Self {
+        MessageWithRecipient {
+            message: message.into(),
+            recipient,
         }
     }
 }